@@ -0,0 +1,64 @@
+#![allow(non_snake_case)]
+
+use std::{error::Error, time::{Duration, Instant}};
+use ui_composer::prelude::signals::signal::{Signal, SignalExt};
+use ui_composer::renderer::engine::render_engine::{HeadlessRenderingEngineDescriptor, RenderingEngine};
+use ui_composer::renderer::modules::ui::to_linear_rgb;
+use ui_composer::renderer::{
+    formats::vertex::InstanceData,
+    modules::ui::PrimitiveRenderModule,
+};
+use ui_composer::ui::animation::{Animated, Animations, Easing, Spring, Transition};
+use ui_composer::ui::reactive::{Rerender, RerenderDriver};
+
+/// Slides a square across the frame while changing its color, ticking the animations by hand
+/// (apps tick them on every frame), and prints the color at the center of the square.
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let mut engine = RenderingEngine::new_headless(HeadlessRenderingEngineDescriptor {
+        size: (64, 64),
+        ..Default::default()
+    }).await?;
+
+    let animations = Animations::new();
+    let square = Animated::new(&animations, square_at([4.0, 24.0], 0xee2244));
+    let width = Animated::new(&animations, 16.0);
+
+    let mut primitive_module = Box::new(PrimitiveRenderModule::from_gpu(&engine.gpu)?);
+    primitive_module.listen_to_splices(RerenderDriver::spawn(Square(&square), None));
+    engine.add_render_module(primitive_module);
+
+    let start = Instant::now();
+    square.animate_to(square_at([44.0, 24.0], 0x2244ee), Transition::tween(Duration::from_millis(200), Easing::Linear));
+    width.animate_to(32.0, Transition::spring(Spring::default()));
+
+    for elapsed in [0, 100, 200] {
+        animations.tick(start + Duration::from_millis(elapsed));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        engine.render()?;
+        let pixels = engine.read_frame_rgba().await?;
+        let center = (square.get().transform[3][0] + 8.0) as usize;
+        println!("{}ms: {:?}", elapsed, &pixels[(32 * 64 + center) * 4..][..4]);
+    }
+
+    // Springs settle on their own time, and the clock stops once they do.
+    let mut elapsed = Duration::ZERO;
+    while animations.tick(start + elapsed) {
+        elapsed += Duration::from_millis(16);
+    }
+    println!("Spring settled at {} after {}ms", width.get(), elapsed.as_millis());
+
+    Ok(())
+}
+
+fn Square(square: &Animated<InstanceData>) -> impl Signal<Item = Rerender> {
+    square.signal().map(|square| Rerender::primitives(0..1, vec![square]))
+}
+
+fn square_at(position: [f32; 2], color: u32) -> InstanceData {
+    InstanceData {
+        transform: InstanceData::rect_transform([position[0], position[1], 0.5], [16.0, 16.0]),
+        color: to_linear_rgb(color),
+        ..Default::default()
+    }
+}
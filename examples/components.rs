@@ -9,7 +9,7 @@ use futures_signals::{
 
 #[derive(Debug, Clone)]
 enum Primitive {
-    Text(AABB, String),
+    Text(Aabb, String),
 }
 
 struct Context {
@@ -18,11 +18,11 @@ struct Context {
 }
 
 #[derive(Debug, Clone, Copy)]
-struct AABB(f32, f32, f32, f32);
+struct Aabb(f32, f32, f32, f32);
 
 #[derive(Debug, Clone, Copy)]
 struct LayoutHints {
-    aabb: AABB,
+    aabb: Aabb,
 }
 
 struct Rerender {
@@ -39,10 +39,10 @@ enum RerenderInner {
 async fn main() {
     // Setting up global states the app can listen to
     let lhints = Mutable::new(LayoutHints {
-        aabb: AABB(0.0, 0.0, 10.0, 10.0),
+        aabb: Aabb(0.0, 0.0, 10.0, 10.0),
     });
 
-    let dummy_text = Mutable::new(format!("Lorem ipsum dolor sit amet!"));
+    let dummy_text = Mutable::new("Lorem ipsum dolor sit amet!".to_owned());
 
     // Packaging them in one neat box.
     let cx = Context {
@@ -57,11 +57,10 @@ async fn main() {
 
     // Changing these values should rerender the respective primitives
     lhints.set(LayoutHints {
-        aabb: AABB(10.0, 10.0, 20.0, 20.0),
-        ..lhints.get()
+        aabb: Aabb(10.0, 10.0, 20.0, 20.0),
     });
 
-    dummy_text.set(format!("This is a new text that has been set!"));
+    dummy_text.set("This is a new text that has been set!".to_owned());
 }
 
 fn App(cx: Context) -> impl Signal<Item = Rerender> {
@@ -71,13 +70,12 @@ fn App(cx: Context) -> impl Signal<Item = Rerender> {
     })
 }
 
-fn Text<S>(aabb: AABB, text_signal: S) -> impl Signal<Item = Rerender>
+fn Text<S>(aabb: Aabb, text_signal: S) -> impl Signal<Item = Rerender>
 where
     S: Signal<Item = String>,
 {
-    let r = text_signal.map(move |s| Rerender {
+    text_signal.map(move |s| Rerender {
         range: 1..2,
         command: RerenderInner::Primitive(Primitive::Text(aabb, s)),
-    });
-    r
+    })
 }
@@ -2,16 +2,30 @@
 
 use std::error::Error;
 use ui_composer::prelude::*;
+use ui_composer::prelude::signals::signal::{Signal, SignalExt};
 use ui_composer::renderer::modules::ui::to_linear_rgb;
 use ui_composer::renderer::{
     formats::vertex::InstanceData,
     modules::ui::PrimitiveRenderModule,
 };
+use ui_composer::ui::docks::{UIFragment, UIFragmentList, UIFragmentSlot};
+use ui_composer::ui::geometry::{FlowDirection, Rect};
+use ui_composer::ui::input::{EventPhase, UIEvent};
+use ui_composer::ui::layout::flow::layout;
+use ui_composer::ui::reactive::{Rerender, RerenderDriver};
 
 struct MyState {
     pub counter: i32,
 }
 
+struct Increment;
+
+impl Action<MyState> for Increment {
+    fn apply(self, state: &mut MyState) {
+        state.counter += 1;
+    }
+}
+
 const TEST_FONT: &[u8; 273900] = include_bytes!("../assets/fonts/JetBrainsMono-Regular.ttf");
 const TEST_FONT2: &[u8; 15920] = include_bytes!("../assets/fonts/Nayten Sans.ttf");
 
@@ -20,12 +34,15 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let initial_state = MyState { counter: 0 };
 
     let mut app = UIAppBuilder::new(initial_state)
-        .with_window_title("My Window".to_owned())
+        .with_window_title("My Window")
         .with_window_size((300, 300))
         .build()
         .await?;
 
-    let mut primitive_module = Box::new(PrimitiveRenderModule::new(&app));
+    let screen = layout(&screen_fragment(), Rect::new(0.0, 0.0, 300.0, 300.0), app.locale().direction);
+    let button_rect = screen.children[1].children[1].rect;
+
+    let mut primitive_module = Box::new(PrimitiveRenderModule::new(&app)?);
 
     // TODO: It should be possible to communicate with this module 
     // from some higher level API.
@@ -33,36 +50,78 @@ async fn main() -> Result<(), Box<dyn Error>> {
         &app.get_render_engine().gpu,
         &get_test_instance_data()
     );
-    
+    primitive_module.listen_to_splices(RerenderDriver::spawn(Button(&app.state, button_rect), Some(app.scheduler().clone())));
+
     app.add_render_module(primitive_module);
 
+    let store = app.state.clone();
+    app.input().set_layout(&screen);
+    let button = app.input().fragment(&[1, 1]);
+    app.input().on_event(button, EventPhase::Bubble, move |event, cx| {
+        if let UIEvent::PointerPressed { .. } = event {
+            store.dispatch(Increment);
+            cx.stop_propagation();
+        }
+    });
+
+    // Keeps the counter running whatever happens, instead of quitting when out of memory.
+    app.set_error_hook(|error| {
+        eprintln!("{}", error);
+        ErrorResponse::Continue
+    });
+
     app.run().await?;
 
     Ok(())
 }
 
+/// The screen, with a button centered at its bottom.
+fn screen_fragment() -> UIFragment {
+    let fixed = |width, height| UIFragment::Leaf(Box::new(UIFragmentSlot {
+        weight: 0.0,
+        minimum_size: (width, height),
+        maximum_size: (width, height),
+        ..Default::default()
+    }));
+    let spacer = || UIFragment::Leaf(Box::new(UIFragmentSlot::default()));
+
+    UIFragment::Container(Box::new(UIFragmentList::new(FlowDirection::Vertical, vec![
+        spacer(),
+        UIFragment::Container(Box::new(
+            UIFragmentList::new(FlowDirection::Forward, vec![spacer(), fixed(96.0, 32.0), spacer()]).with_weight(0.0)
+        )),
+        fixed(0.0, 16.0),
+    ])))
+}
+
+/// The button changes color every time it's clicked.
+fn Button(state: &Store<MyState>, rect: Rect) -> impl Signal<Item = Rerender> {
+    const COLORS: [u32; 3] = [0xee2244, 0x22aa44, 0x2244ee];
+
+    state.signal_ref(|state| state.counter).map(move |counter| Rerender::primitives(1..2, vec![
+        button(rect, COLORS[counter as usize % COLORS.len()]),
+    ]))
+}
+
 fn get_test_instance_data() -> Vec<InstanceData> {
     vec![
         InstanceData {
-            transform: rect([0.0, 0.0, 0.999], [300.0, 300.0]),
+            transform: InstanceData::rect_transform([0.0, 0.0, 0.999], [300.0, 300.0]),
             color: to_linear_rgb(0xdedede),
-        },
-        InstanceData {
-            transform: rect([(300.0-96.0-4.0)/2.0, 300.0-32.0-16.0+4.0, 0.91], [96.0+4.0, 32.0]),
-            color: to_linear_rgb(0xa0a0a0),
-        },
-        InstanceData {
-            transform: rect([(300.0-96.0)/2.0, 300.0-32.0-16.0, 0.9], [96.0, 32.0]),
-            color: to_linear_rgb(0xee2244),
+            ..Default::default()
         },
     ]
 }
 
-fn rect(position: [f32; 3], size: [f32; 2]) -> [[f32; 4]; 4] {
-    [
-        [size[0], 0.0, 0.0, 0.0],
-        [0.0, size[1], 0.0, 0.0],
-        [0.0, 0.0, 1.0, 0.0],
-        [position[0], position[1], position[2], 1.0]
-    ]
+fn button(bounds: Rect, color: u32) -> InstanceData {
+    InstanceData {
+        transform: InstanceData::rect_transform([bounds.x, bounds.y, 0.9], [bounds.width, bounds.height]),
+        color: to_linear_rgb(color),
+        corner_radii: [8.0; 4],
+        border_color: to_linear_rgb(0xa01030),
+        border_width: 2.0,
+        shadow_offset: [0.0, 4.0],
+        shadow_blur: 6.0,
+        shadow_color: [0.0, 0.0, 0.0, 0.35],
+    }
 }
@@ -0,0 +1,101 @@
+use std::error::Error;
+use ui_composer::ui::docks::layout_file::LayoutFile;
+use ui_composer::ui::docks::workspace::{DockNode, PanelId, Workspace};
+use ui_composer::ui::docks::{UIFragment, UIFragmentList, UIFragmentSlot};
+use ui_composer::ui::geometry::{FlowDirection, Rect, WritingDirection};
+use ui_composer::ui::input::UIEvent;
+use winit::event::MouseButton;
+
+/// Builds an editor-like workspace, then rearranges it with pointer events like a user would:
+/// dragging a divider, docking a panel below another, and tearing one out.
+/// Saves the result as a named layout, along with the fragment tree of a panel, and loads it back.
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut workspace = Workspace::new("Editor", DockNode::split(FlowDirection::Forward, vec![
+        DockNode::tabs([PanelId::from("files"), PanelId::from("search")]),
+        DockNode::tabs([PanelId::from("editor")]),
+        DockNode::tabs([PanelId::from("outline")]),
+    ]));
+    let bounds = Rect::new(0.0, 0.0, 1200.0, 800.0);
+
+    // Drag the divider between the files and the editor 100 pixels to the right.
+    let layout = workspace.layout(bounds, WritingDirection::LeftToRight);
+    let divider = layout.dividers[0].rect;
+    let grab = (divider.x + divider.width / 2.0, 400.0);
+    drag(&mut workspace, bounds, grab, (grab.0 + 100.0, grab.1));
+    print_panes(&workspace, bounds);
+
+    // Drag the search tab to the bottom of the editor.
+    let layout = workspace.layout(bounds, WritingDirection::LeftToRight);
+    let search = tab_center(&layout, "search");
+    let editor = layout.panes.iter().find(|pane| pane.active == Some(PanelId::from("editor"))).unwrap().rect;
+    drag(&mut workspace, bounds, search, (editor.x + editor.width / 2.0, editor.y + editor.height - 10.0));
+    print_panes(&workspace, bounds);
+
+    // Tear the outline out of the workspace.
+    let layout = workspace.layout(bounds, WritingDirection::LeftToRight);
+    let outline = tab_center(&layout, "outline");
+    drag(&mut workspace, bounds, outline, (1300.0, 100.0));
+    print_panes(&workspace, bounds);
+
+    let mut layout = workspace.to_layout();
+    layout.set_fragment(PanelId::from("files"), &UIFragment::Container(Box::new(
+        UIFragmentList::new(FlowDirection::Vertical, vec![
+            UIFragment::Leaf(Box::new(UIFragmentSlot {
+                name: Some("toolbar".to_owned()),
+                minimum_size: (0.0, 32.0),
+                maximum_size: (f64::INFINITY, 32.0),
+                ..Default::default()
+            })),
+            UIFragment::Leaf(Box::new(UIFragmentSlot { name: Some("tree".to_owned()), ..Default::default() })),
+        ]).with_gap(2.0),
+    )));
+    let mut layouts = LayoutFile::new();
+    layouts.set_layout(layout);
+
+    let path = std::env::temp_dir().join("ui_composer_layouts.ron");
+    layouts.save(&path)?;
+    println!("{}", std::fs::read_to_string(&path)?);
+    let loaded = LayoutFile::load(&path)?;
+    assert_eq!(loaded, layouts);
+    let workspace = Workspace::from_layout(loaded.layout("Editor").unwrap());
+
+    // Right-to-left locales mirror the same arrangement.
+    println!("Mirrored:");
+    for pane in workspace.layout(bounds, WritingDirection::RightToLeft).panes {
+        println!("  {:?} at {:?}", pane.active, pane.rect);
+    }
+
+    Ok(())
+}
+
+/// Presses at `from`, moves to `to` in a few steps, and releases there.
+fn drag(workspace: &mut Workspace, bounds: Rect, from: (f32, f32), to: (f32, f32)) {
+    let events = [
+        UIEvent::PointerPressed { position: from, button: MouseButton::Left },
+        UIEvent::PointerMoved { position: ((from.0 + to.0) / 2.0, (from.1 + to.1) / 2.0) },
+        UIEvent::PointerMoved { position: to },
+        UIEvent::PointerReleased { position: to, button: MouseButton::Left },
+    ];
+    for event in events {
+        // Apps lay the workspace out again every frame, so the layout is always up to date.
+        let layout = workspace.layout(bounds, WritingDirection::LeftToRight);
+        workspace.handle_event(&event, &layout);
+    }
+}
+
+fn tab_center(layout: &ui_composer::ui::docks::workspace::DockLayout, panel: &str) -> (f32, f32) {
+    let (_, rect) = layout.panes
+        .iter()
+        .flat_map(|pane| pane.tabs.iter())
+        .find(|(id, _)| id == &PanelId::from(panel))
+        .unwrap();
+    (rect.x + rect.width / 2.0, rect.y + rect.height / 2.0)
+}
+
+fn print_panes(workspace: &Workspace, bounds: Rect) {
+    println!("Panes:");
+    for pane in workspace.layout(bounds, WritingDirection::LeftToRight).panes {
+        let panels: Vec<&str> = pane.tabs.iter().map(|(id, _)| id.0.as_str()).collect();
+        println!("  {:?} at {:?}", panels, pane.rect);
+    }
+}
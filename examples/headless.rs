@@ -0,0 +1,41 @@
+use std::error::Error;
+use ui_composer::renderer::engine::render_engine::{HeadlessRenderingEngineDescriptor, RenderingEngine};
+use ui_composer::renderer::modules::ui::{to_linear_rgb, to_linear_rgba};
+use ui_composer::renderer::{
+    formats::vertex::InstanceData,
+    modules::ui::PrimitiveRenderModule,
+};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let mut engine = RenderingEngine::new_headless(HeadlessRenderingEngineDescriptor {
+        size: (64, 64),
+        ..Default::default()
+    }).await?;
+
+    let mut primitive_module = Box::new(PrimitiveRenderModule::from_gpu(&engine.gpu)?);
+    primitive_module.push_raw_primitives(&engine.gpu, &vec![
+        // A translucent overlay, in front of the rectangle even though it's submitted first.
+        InstanceData {
+            transform: InstanceData::rect_transform([32.0, 0.0, 0.25], [32.0, 64.0]),
+            color: to_linear_rgba(0x2244ee80),
+            ..Default::default()
+        },
+        InstanceData {
+            transform: InstanceData::rect_transform([16.0, 16.0, 0.5], [32.0, 32.0]),
+            color: to_linear_rgb(0xee2244),
+            ..Default::default()
+        },
+    ]);
+    engine.add_render_module(primitive_module);
+
+    engine.render()?;
+    let pixels = engine.read_frame_rgba().await?;
+
+    let pixel_at = |x: usize, y: usize| &pixels[(y * 64 + x) * 4..][..4];
+    println!("Corner pixel: {:?}", pixel_at(0, 0));
+    println!("Rectangle pixel: {:?}", pixel_at(24, 32));
+    println!("Overlay pixel: {:?}", pixel_at(40, 32));
+
+    Ok(())
+}
@@ -0,0 +1,55 @@
+#![allow(non_snake_case)]
+
+use std::{error::Error, time::Duration};
+use ui_composer::prelude::signals::signal::{always, Mutable, Signal, SignalExt};
+use ui_composer::renderer::engine::render_engine::{HeadlessRenderingEngineDescriptor, RenderingEngine};
+use ui_composer::renderer::modules::ui::to_linear_rgb;
+use ui_composer::renderer::{
+    formats::vertex::InstanceData,
+    modules::ui::PrimitiveRenderModule,
+};
+use ui_composer::ui::reactive::{Rerender, RerenderDriver};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let mut engine = RenderingEngine::new_headless(HeadlessRenderingEngineDescriptor {
+        size: (64, 64),
+        ..Default::default()
+    }).await?;
+
+    let color = Mutable::new(0xee2244);
+
+    let mut primitive_module = Box::new(PrimitiveRenderModule::from_gpu(&engine.gpu)?);
+    primitive_module.listen_to_splices(RerenderDriver::spawn(App(color.clone()), None));
+    engine.add_render_module(primitive_module);
+
+    for new_color in [0x22ee44, 0x2244ee] {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        engine.render()?;
+        let pixels = engine.read_frame_rgba().await?;
+        println!("Button pixel: {:?}", &pixels[(32 * 64 + 32) * 4..][..4]);
+
+        color.set(new_color);
+    }
+
+    Ok(())
+}
+
+fn App(color: Mutable<u32>) -> impl Signal<Item = Rerender> {
+    color.signal().map(|color| Rerender::nested(0..2, Button(color)))
+}
+
+fn Button(color: u32) -> impl Signal<Item = Rerender> {
+    always(color).map(|color| Rerender::primitives(0..2, vec![
+        InstanceData {
+            transform: InstanceData::rect_transform([14.0, 14.0, 0.6], [40.0, 40.0]),
+            color: to_linear_rgb(0xa0a0a0),
+            ..Default::default()
+        },
+        InstanceData {
+            transform: InstanceData::rect_transform([16.0, 16.0, 0.5], [32.0, 32.0]),
+            color: to_linear_rgb(color),
+            ..Default::default()
+        },
+    ]))
+}
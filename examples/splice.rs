@@ -2,10 +2,13 @@
 
 use std::rc::Rc;
 
+/// Makes the primitives that replace the ones in a range.
+type Replacer = Rc<dyn Fn() -> Box<dyn Iterator<Item = Primitive>>>;
+
 #[derive(Clone)]
 struct UIRange {
     range: std::ops::Range<usize>,
-    replacer: Option<Rc<dyn Fn() -> Box<dyn Iterator<Item = Primitive>>>>
+    replacer: Option<Replacer>
 }
 
 #[derive(Debug, Clone)]
@@ -0,0 +1,78 @@
+use std::error::Error;
+use ui_composer::app::windows::WindowDescriptor;
+use ui_composer::prelude::*;
+use ui_composer::renderer::modules::ui::to_linear_rgb;
+use ui_composer::renderer::{
+    formats::vertex::InstanceData,
+    modules::ui::PrimitiveRenderModule,
+};
+use ui_composer::ui::geometry::Rect;
+use ui_composer::ui::input::{EventPhase, UIEvent};
+use ui_composer::ui::layout::flow::LayoutBox;
+
+/// Clicking the main window opens a new window, and clicking that window closes it.
+/// The main window's title counts how many windows were opened.
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let mut app = UIAppBuilder::new(())
+        .with_window_title("Main Window")
+        .with_window_size((300, 300))
+        .build()
+        .await?;
+
+    let mut primitive_module = Box::new(PrimitiveRenderModule::new(&app)?);
+    primitive_module.push_raw_primitives(&app.get_render_engine().gpu, &vec![
+        InstanceData {
+            transform: InstanceData::rect_transform([0.0, 0.0, 0.5], [300.0, 300.0]),
+            color: to_linear_rgb(0xdedede),
+            ..Default::default()
+        },
+    ]);
+    app.add_render_module(primitive_module);
+
+    let windows = app.window_manager();
+    let main_window = app.main_window_handle();
+    let mut opened_count = 0;
+    app.input().set_layout(&LayoutBox { rect: Rect::new(0.0, 0.0, 300.0, 300.0), children: Vec::new() });
+    let background = app.input().fragment(&[]);
+    app.input().on_event(background, EventPhase::Bubble, move |event, _| {
+        if let UIEvent::PointerPressed { .. } = event {
+            let windows_inside = windows.clone();
+            let opened = windows.open_window(WindowDescriptor {
+                title: "Secondary Window".to_owned(),
+                size: (200, 200),
+            }, move |engine| {
+                let mut primitive_module = Box::new(PrimitiveRenderModule::from_gpu(&engine.gpu)?);
+                primitive_module.push_raw_primitives(&engine.gpu, &vec![
+                    InstanceData {
+                        transform: InstanceData::rect_transform([0.0, 0.0, 0.5], [200.0, 200.0]),
+                        color: to_linear_rgb(0x2244ee),
+                        ..Default::default()
+                    },
+                ]);
+                engine.add_render_module(primitive_module);
+
+                let id = engine.window_id().unwrap();
+                engine.input.set_layout(&LayoutBox { rect: Rect::new(0.0, 0.0, 200.0, 200.0), children: Vec::new() });
+                let background = engine.input.fragment(&[]);
+                engine.input.on_event(background, EventPhase::Bubble, move |event, _| {
+                    if let UIEvent::PointerPressed { .. } = event {
+                        let _ = windows_inside.close_window(id);
+                    }
+                });
+
+                Ok(())
+            });
+
+            if let Err(e) = opened {
+                eprintln!("{:?}", e);
+            }
+
+            opened_count += 1;
+            let _ = main_window.set_title(format!("Main Window ({} opened)", opened_count));
+        }
+    });
+
+    app.run().await?;
+    Ok(())
+}
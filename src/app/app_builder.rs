@@ -1,6 +1,6 @@
-use std::{error::Error, str::Chars};
+use std::str::Chars;
 
-use crate::renderer::modules::ui::PrimitiveRenderModule;
+use crate::{error::UIError, renderer::{device::GPUOptions, modules::ui::PrimitiveRenderModule}, ui::locale::Locale};
 
 use super::UIApp;
 
@@ -8,6 +8,9 @@ pub struct UIAppBuilder<TState> {
     initial_state: TState,
     window_title: String,
     window_size: (i32, i32),
+    gpu_options: GPUOptions,
+    continuous_rendering: bool,
+    locale: Locale,
 }
 
 impl<TState> UIAppBuilder<TState> {
@@ -16,30 +19,98 @@ impl<TState> UIAppBuilder<TState> {
             initial_state,
             window_title: "Untitled App".to_owned(),
             window_size: (640, 360),
+            gpu_options: GPUOptions::default(),
+            continuous_rendering: false,
+            locale: Locale::default(),
         }
     }
 
-    /// Changes the window title of the main app window. You can change this later when the app is already running.
-    pub fn with_window_title<'a, I: AsRef<str>>(mut self, title: I) -> Self {
+    /// Changes the window title of the main app window.
+    /// You can change this later when the app is already running, with [`super::UIApp::main_window_handle`].
+    pub fn with_window_title<I: AsRef<str>>(mut self, title: I) -> Self {
         self.window_title.clear();
         self.window_title.push_str(title.as_ref());
         self
     }
 
-    /// Changes the window size of the main app window. You can change this later when the app is already running.
+    /// Changes the window size of the main app window.
+    /// You can change this later when the app is already running, with [`super::UIApp::main_window_handle`].
     /// This will have no effect if the window is fullscreen (for example, running in mobile).
     pub fn with_window_size(mut self, size: (i32, i32)) -> Self {
         self.window_size = size;
         self
     }
 
+    /// Restricts the graphics APIs the app may render with. All of them are allowed by default.
+    pub fn with_backends(mut self, backends: wgpu::Backends) -> Self {
+        self.gpu_options.backends = backends;
+        self
+    }
+
+    /// Chooses between an integrated (low power) and a discrete (high performance) GPU, if there are both.
+    pub fn with_power_preference(mut self, power_preference: wgpu::PowerPreference) -> Self {
+        self.gpu_options.power_preference = power_preference;
+        self
+    }
+
+    /// Makes the app only render with a software (fallback) adapter.
+    pub fn with_fallback_adapter(mut self, force_fallback_adapter: bool) -> Self {
+        self.gpu_options.force_fallback_adapter = force_fallback_adapter;
+        self
+    }
+
+    /// Changes how frames are presented to the app's windows.
+    /// If a window doesn't support this mode, the first mode it supports is used instead.
+    pub fn with_present_mode(mut self, present_mode: wgpu::PresentMode) -> Self {
+        self.gpu_options.present_mode = present_mode;
+        self
+    }
+
+    /// Turns vsync on or off, letting the platform choose the present mode. Vsync is on by default.
+    pub fn with_vsync(self, vsync: bool) -> Self {
+        self.with_present_mode(if vsync {
+            wgpu::PresentMode::AutoVsync
+        } else {
+            wgpu::PresentMode::AutoNoVsync
+        })
+    }
+
+    /// Changes how the app's windows are composited with what's behind them.
+    /// If a window doesn't support this mode, the first mode it supports is used instead.
+    pub fn with_alpha_mode(mut self, alpha_mode: wgpu::CompositeAlphaMode) -> Self {
+        self.gpu_options.alpha_mode = alpha_mode;
+        self
+    }
+
+    /// Sets every GPU option at once.
+    pub fn with_gpu_options(mut self, gpu_options: GPUOptions) -> Self {
+        self.gpu_options = gpu_options;
+        self
+    }
+
+    /// Makes the app redraw its windows every frame, instead of only when something changes.
+    /// You can change this later when the app is already running, with [`super::UIApp::scheduler`].
+    pub fn with_continuous_rendering(mut self, continuous: bool) -> Self {
+        self.continuous_rendering = continuous;
+        self
+    }
+
+    /// Sets the language and writing direction of the app. English, written left to right, by default.
+    pub fn with_locale(mut self, locale: Locale) -> Self {
+        self.locale = locale;
+        self
+    }
+
     /// Builds the UI App.
-    pub async fn build(self) -> Result<UIApp<TState>, Box<dyn Error>> {
+    pub async fn build(self) -> Result<UIApp<TState>, UIError> {
         let app = UIApp::new(
             self.initial_state,
             super::UIAppCreateDescriptor {
                 initial_window_title: self.window_title,
                 initial_window_size: self.window_size,
+                gpu_options: self.gpu_options,
+                continuous_rendering: self.continuous_rendering,
+                locale: self.locale,
             },
         ).await?;
 
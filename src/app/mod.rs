@@ -1,19 +1,46 @@
-use crate::renderer::engine::{render_module::RenderModule, render_engine::{RenderingEngine, SharedRenderModule}};
-use std::{error::Error, sync::{Arc, Mutex}};
-use winit::{event::WindowEvent, event_loop::EventLoop, window::Window};
+use crate::error::{default_error_hook, ErrorHook, ErrorResponse, UIError};
+use crate::ui::animation::Animations;
+use crate::ui::locale::Locale;
+use crate::ui::input::InputRouter;
+use crate::renderer::device::GPUOptions;
+use crate::renderer::fonts::FontRegistry;
+use crate::renderer::engine::{render_module::RenderModule, render_engine::{RenderModuleHandle, RenderingEngine, SharedRenderModule}};
+use std::{collections::HashMap, path::Path, sync::{Arc, Mutex}, task::Poll, time::Instant};
+use winit::{event::WindowEvent, event_loop::{EventLoop, EventLoopBuilder, EventLoopWindowTarget}, window::{Window, WindowId}};
 
 pub mod app_builder;
+pub mod scheduler;
+pub mod store;
+pub mod windows;
+
+use scheduler::FrameScheduler;
+use store::Store;
+use windows::{AppEvent, WindowDescriptor, WindowHandle, WindowManager};
+
 /// A user interface app, everything necessary for rendering UI from state.
 pub struct UIApp<TState> {
-    pub state: TState,
-    event_loop: EventLoop<()>,
-    render_engine: RenderingEngine,
+    /// The app's state. Clone it into event handlers to dispatch actions that change it.
+    pub state: Store<TState>,
+    event_loop: EventLoop<AppEvent>,
+    /// The rendering engines of every open window, which all share the main window's device.
+    windows: HashMap<WindowId, RenderingEngine>,
+    main_window: WindowId,
+    fonts: FontRegistry,
+    error_hook: ErrorHook,
+    scheduler: FrameScheduler,
+    animations: Animations,
 }
 
 /// Descriptor for creating a new UI App.
 pub struct UIAppCreateDescriptor {
     pub initial_window_title: String,
     pub initial_window_size: (i32, i32),
+    /// Options for choosing the GPU, shared by every window of the app.
+    pub gpu_options: GPUOptions,
+    /// Whether windows are redrawn every frame, instead of only when invalidated. See [`FrameScheduler`].
+    pub continuous_rendering: bool,
+    /// Language and writing direction of the app, see [`UIApp::set_locale`].
+    pub locale: Locale,
 }
 
 impl Default for UIAppCreateDescriptor {
@@ -21,6 +48,9 @@ impl Default for UIAppCreateDescriptor {
         Self {
             initial_window_title: "UI Composer App".to_owned(),
             initial_window_size: (640, 360),
+            gpu_options: GPUOptions::default(),
+            continuous_rendering: false,
+            locale: Locale::default(),
         }
     }
 }
@@ -30,58 +60,270 @@ impl<TState> UIApp<TState> {
     pub async fn new(
         initial_state: TState,
         descriptor: UIAppCreateDescriptor,
-    ) -> Result<Self, Box<dyn Error>> {
-        let event_loop = EventLoop::new();
-        let window = winit::window::WindowBuilder::new()
-            .with_title(descriptor.initial_window_title)
-            .with_inner_size(winit::dpi::LogicalSize {
-                width: descriptor.initial_window_size.0,
-                height: descriptor.initial_window_size.1,
-            })
-            .with_visible(false)
-            .build(&event_loop)?;
-        let render_engine = RenderingEngine::new(window).await?;
+    ) -> Result<Self, UIError> {
+        let event_loop = EventLoopBuilder::with_user_event().build();
+        let window = WindowDescriptor {
+            title: descriptor.initial_window_title,
+            size: descriptor.initial_window_size,
+        }.build(&event_loop)?;
+        let main_window = window.id();
+        let mut render_engine = RenderingEngine::new(window, descriptor.gpu_options).await?;
+        render_engine.set_locale(descriptor.locale);
+
+        let scheduler = FrameScheduler::new();
+        scheduler.set_continuous(descriptor.continuous_rendering);
+        let proxy = event_loop.create_proxy();
+        scheduler.set_waker(move || {
+            let _ = proxy.send_event(AppEvent::Wake);
+        });
 
         Ok(Self {
-            state: initial_state,
+            state: Store::with_scheduler(initial_state, scheduler.clone()),
+            animations: Animations::with_scheduler(scheduler.clone()),
             event_loop,
-            render_engine,
+            windows: HashMap::from([(main_window, render_engine)]),
+            main_window,
+            fonts: FontRegistry::new(),
+            error_hook: Box::new(default_error_hook),
+            scheduler,
         })
     }
 
-    pub fn add_render_module(&mut self, primitive_module: SharedRenderModule) {
-        self.render_engine.add_render_module(primitive_module);
+    /// Adds a render module to the main window.
+    pub fn add_render_module(&mut self, primitive_module: SharedRenderModule) -> RenderModuleHandle {
+        self.get_render_engine_mut().add_render_module(primitive_module)
+    }
+
+    /// Adds a render module to a layer of the main window. See [`RenderingEngine::add_render_module_to_layer`].
+    pub fn add_render_module_to_layer(&mut self, render_module: SharedRenderModule, layer: i32) -> RenderModuleHandle {
+        self.get_render_engine_mut().add_render_module_to_layer(render_module, layer)
+    }
+
+    pub fn main_window_id(&self) -> WindowId {
+        self.main_window
+    }
+
+    /// Opens another window, which renders with the same device as the main one.
+    /// Its rendering engine can be reached with [`UIApp::get_window_engine_mut`] to add render modules to it.
+    ///
+    /// To open windows once the app is running, use a [`WindowManager`].
+    pub fn open_window(&mut self, descriptor: WindowDescriptor) -> Result<WindowId, UIError> {
+        let render_engine = open_window(&self.event_loop, &descriptor, &self.windows[&self.main_window])?;
+        let id = render_engine.window_id().expect("Engines created for windows always render to them.");
+        self.windows.insert(id, render_engine);
+        Ok(id)
+    }
+
+    /// Closes a window that isn't the main one. Returns whether there was such a window.
+    pub fn close_window(&mut self, id: WindowId) -> bool {
+        id != self.main_window && self.windows.remove(&id).is_some()
+    }
+
+    /// Ids of every open window, including the main one.
+    pub fn window_ids(&self) -> Vec<WindowId> {
+        self.windows.keys().copied().collect()
+    }
+
+    pub fn get_window_engine(&self, id: WindowId) -> Option<&RenderingEngine> {
+        self.windows.get(&id)
+    }
+
+    pub fn get_window_engine_mut(&mut self, id: WindowId) -> Option<&mut RenderingEngine> {
+        self.windows.get_mut(&id)
+    }
+
+    /// Returns a manager for opening and closing windows while the app runs.
+    pub fn window_manager(&self) -> WindowManager {
+        WindowManager { proxy: self.event_loop.create_proxy() }
+    }
+
+    /// Returns a handle for changing a window (like its title or size) while the app runs.
+    pub fn window_handle(&self, id: WindowId) -> WindowHandle {
+        self.window_manager().window(id)
+    }
+
+    /// Returns a handle for changing the main window while the app runs.
+    pub fn main_window_handle(&self) -> WindowHandle {
+        self.window_handle(self.main_window)
     }
 
-    /// Loads font data from a buffer into the text rendering engine.
-    pub fn load_font_data(&mut self, bytes: Vec<u8>) {
-        //TODO: Load font data into the inner font db.
-        //Maybe this will end up being in TextRenderer?
+    /// Loads font data from a buffer into the app's fonts, making it available to every text module.
+    pub fn load_font_data(&mut self, bytes: Vec<u8>) -> Result<(), UIError> {
+        self.fonts.load_font_data(bytes)
     }
 
+    /// Loads a font file into the app's fonts, making it available to every text module.
+    pub fn load_font_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), UIError> {
+        self.fonts.load_font_file(path)
+    }
+
+    /// The fonts shared by the app's text modules. Also where the default families are set.
+    pub fn fonts(&self) -> &FontRegistry {
+        &self.fonts
+    }
+
+    /// The rendering engine of the main window.
     pub fn get_render_engine(&self) -> &RenderingEngine {
-        &self.render_engine
+        &self.windows[&self.main_window]
+    }
+
+    /// The rendering engine of the main window.
+    pub fn get_render_engine_mut(&mut self) -> &mut RenderingEngine {
+        self.windows.get_mut(&self.main_window).expect("The main window is only closed when the app quits.")
+    }
+
+    /// The input router of the main window, where fragments and their event handlers are registered.
+    pub fn input(&mut self) -> &mut InputRouter {
+        &mut self.get_render_engine_mut().input
+    }
+
+    /// The scheduler deciding when the app's windows are redrawn.
+    /// Clone it into whatever changes what's on the screen outside of the app's state, to invalidate the windows.
+    pub fn scheduler(&self) -> &FrameScheduler {
+        &self.scheduler
+    }
+
+    /// The clock of the app's animations, which ticks right before every frame.
+    /// Create [`crate::ui::animation::Animated`] values with it.
+    /// What components render from animated values shows up a frame late, see [`Animations`].
+    pub fn animations(&self) -> &Animations {
+        &self.animations
+    }
+
+    /// The language and writing direction of the app.
+    pub fn locale(&self) -> &Locale {
+        self.get_render_engine().locale()
+    }
+
+    /// Changes the language and writing direction of the app, which every window and render module follows.
+    /// Lay fragments out with its direction (see [`crate::ui::layout::flow::layout`]) to mirror them for right-to-left languages.
+    pub fn set_locale(&mut self, locale: Locale) {
+        for render_engine in self.windows.values_mut() {
+            render_engine.set_locale(locale.clone());
+        }
+        self.scheduler.invalidate();
+    }
+
+    /// Sets the function that receives the errors that happen while the app runs,
+    /// like render modules failing, windows failing to open or the GPU reporting errors, and decides whether the app keeps running.
+    ///
+    /// By default, errors are printed, and the app quits when out of memory.
+    pub fn set_error_hook<F>(&mut self, hook: F)
+    where
+        F: FnMut(&UIError) -> ErrorResponse + 'static,
+    {
+        self.error_hook = Box::new(hook);
     }
 
     /// Takes ownership of the current app and runs it, listening for external input.
     ///
     /// At this stage, you can no longer directly interact with the app from the outside,
     /// so make sure to set all the input handlers and state you might want *inside* it.
-    pub async fn run(mut self) -> Result<(), Box<dyn Error>> {
-        self.render_engine.window().set_visible(true);
+    pub async fn run(mut self) -> Result<(), UIError> {
+        for render_engine in self.windows.values() {
+            if let Some(window) = render_engine.window() {
+                window.set_visible(true);
+            }
+        }
 
         self.event_loop
-            .run(move |event, _, mut control_flow| match event {
+            .run(move |event, target, control_flow| match event {
                 winit::event::Event::WindowEvent {
                     event: ref win_event,
                     window_id,
                 } => {
-                    let _ = self.render_engine.handle_input(win_event, control_flow);
-                    handle_basic_window_events(&mut self.render_engine, win_event, control_flow);
+                    let Some(render_engine) = self.windows.get_mut(&window_id) else {
+                        return;
+                    };
+                    // Handlers usually change what's on screen, so windows are redrawn next frame.
+                    if render_engine.handle_input(win_event) {
+                        self.scheduler.invalidate();
+                    }
+
+                    if let WindowEvent::CloseRequested = win_event {
+                        close_window(&mut self.windows, self.main_window, window_id, control_flow);
+                    } else {
+                        handle_basic_window_events(render_engine, win_event, control_flow);
+                    }
                 }
 
                 winit::event::Event::RedrawRequested(window_id) => {
-                    self.render_engine.request_redraw(&mut control_flow)
+                    // Every window shares the device, so they all recover together, with the main window's new device.
+                    if self.windows.get(&window_id).is_some_and(|engine| engine.is_device_lost()) {
+                        for e in recover_device(&mut self.windows, self.main_window, &self.scheduler) {
+                            report_error(&mut self.error_hook, e, control_flow);
+                        }
+                        if self.windows.get(&window_id).is_some_and(|engine| engine.is_device_lost()) {
+                            return;
+                        }
+                    }
+
+                    if let Some(render_engine) = self.windows.get_mut(&window_id) {
+                        if let Err(e) = render_engine.request_redraw() {
+                            report_error(&mut self.error_hook, e, control_flow);
+                        }
+                        for e in render_engine.take_gpu_errors() {
+                            report_error(&mut self.error_hook, e, control_flow);
+                        }
+                    }
+                }
+
+                winit::event::Event::UserEvent(AppEvent::OpenWindow(descriptor, setup)) => {
+                    let opened = open_window(target, &descriptor, &self.windows[&self.main_window])
+                        .and_then(|mut render_engine| setup(&mut render_engine).map(|_| render_engine));
+                    match opened {
+                        Ok(render_engine) => {
+                            if let Some(window) = render_engine.window() {
+                                window.set_visible(true);
+                                self.windows.insert(window.id(), render_engine);
+                                self.scheduler.invalidate();
+                            }
+                        }
+                        Err(e) => report_error(&mut self.error_hook, e, control_flow),
+                    }
+                }
+
+                winit::event::Event::UserEvent(AppEvent::CloseWindow(window_id)) => {
+                    close_window(&mut self.windows, self.main_window, window_id, control_flow);
+                }
+
+                winit::event::Event::UserEvent(AppEvent::WindowCommand(window_id, command)) => {
+                    if let Some(window) = self.windows.get(&window_id).and_then(|engine| engine.window()) {
+                        command.apply(window);
+                    }
+                }
+
+                // Invalidations are handled once all other events were, below.
+                winit::event::Event::UserEvent(AppEvent::Wake) => {}
+
+                winit::event::Event::MainEventsCleared => {
+                    // Errors the GPU reported while handling events, like while setting up a new window.
+                    // Windows share the device, and so its errors.
+                    if let Some(render_engine) = self.windows.get(&self.main_window) {
+                        for e in render_engine.take_gpu_errors() {
+                            report_error(&mut self.error_hook, e, control_flow);
+                        }
+                    }
+
+                    // Running animations invalidate the scheduler, so they keep it awake until they finish.
+                    // Their new values are rendered into splices on the drivers' tasks, which usually haven't run yet
+                    // by the time this frame is drawn, so animations lag a frame behind,
+                    // and the final value is drawn by the extra redraw its splices ask for.
+                    self.animations.tick(Instant::now());
+
+                    // winit coalesces redraw requests, so each window is redrawn at most once per frame,
+                    // and presenting with vsync keeps frames from coming faster than the display shows them.
+                    if self.scheduler.take_frame() {
+                        for render_engine in self.windows.values_mut() {
+                            render_engine.request_window_redraw();
+                        }
+                    }
+
+                    if self.scheduler.is_continuous() {
+                        control_flow.set_poll();
+                    } else {
+                        control_flow.set_wait();
+                    }
                 }
 
                 _ => {}
@@ -89,14 +331,84 @@ impl<TState> UIApp<TState> {
     }    
 }
 
+/// Opens a window with a rendering engine that shares the device and the locale of `main_engine`.
+fn open_window<T>(
+    target: &EventLoopWindowTarget<T>,
+    descriptor: &WindowDescriptor,
+    main_engine: &RenderingEngine,
+) -> Result<RenderingEngine, UIError> {
+    let window = descriptor.build(target)?;
+    let mut render_engine = RenderingEngine::new_sharing(window, main_engine.gpu.shared())?;
+    render_engine.set_locale(main_engine.locale().clone());
+    Ok(render_engine)
+}
+
+/// Replaces the lost device of the main window, and gives the new one to every other window.
+/// The new device is requested without blocking the event loop: while it's pending, `scheduler` is invalidated,
+/// so this is called again next frame. Returns the errors that happened along the way.
+fn recover_device(
+    windows: &mut HashMap<WindowId, RenderingEngine>,
+    main_window: WindowId,
+    scheduler: &FrameScheduler,
+) -> Vec<UIError> {
+    let Some(main_engine) = windows.get_mut(&main_window) else {
+        return Vec::new();
+    };
+    let mut errors = Vec::new();
+    match main_engine.poll_device_recovery() {
+        Poll::Pending => {
+            scheduler.invalidate();
+            return errors;
+        }
+        Poll::Ready(Err(e)) => {
+            errors.push(e);
+            if main_engine.is_device_lost() {
+                return errors;
+            }
+        }
+        Poll::Ready(Ok(())) => {}
+    }
+
+    let shared = main_engine.gpu.shared();
+    for (id, render_engine) in windows.iter_mut() {
+        if *id != main_window {
+            if let Err(e) = render_engine.use_shared_gpu(shared.clone()) {
+                errors.push(e);
+            }
+        }
+    }
+    // The other windows haven't been drawn with the new device yet.
+    scheduler.invalidate();
+    errors
+}
+
+/// Gives an error to the app's error hook, quitting if it says so.
+fn report_error(error_hook: &mut ErrorHook, error: UIError, control_flow: &mut winit::event_loop::ControlFlow) {
+    if error_hook(&error) == ErrorResponse::Exit {
+        *control_flow = winit::event_loop::ControlFlow::Exit;
+    }
+}
+
+/// Closes a window, quitting the app if it's the main window.
+fn close_window(
+    windows: &mut HashMap<WindowId, RenderingEngine>,
+    main_window: WindowId,
+    window_id: WindowId,
+    control_flow: &mut winit::event_loop::ControlFlow,
+) {
+    if window_id == main_window {
+        *control_flow = winit::event_loop::ControlFlow::Exit;
+    } else {
+        windows.remove(&window_id);
+    }
+}
+
 fn handle_basic_window_events(render_engine: &mut RenderingEngine, win_event: &WindowEvent<'_>, control_flow: &mut winit::event_loop::ControlFlow) {
     match win_event {
-        winit::event::WindowEvent::CloseRequested {} =>
-            { *control_flow = winit::event_loop::ControlFlow::Exit }
         winit::event::WindowEvent::Resized(physical_size) =>
             { render_engine.resize_window(*physical_size) }
         winit::event::WindowEvent::ScaleFactorChanged { new_inner_size, .. } =>
             { render_engine.resize_window(**new_inner_size) }
         _ => {}
     }
-}
\ No newline at end of file
+}
\ No newline at end of file
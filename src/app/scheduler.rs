@@ -0,0 +1,132 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
+/// Decides when an app's windows are redrawn.
+///
+/// By default, windows are only redrawn after something invalidates them (like the state changing,
+/// a component rerendering or an input handler running), and the app sleeps in between.
+/// Many invalidations before the next frame result in a single redraw.
+/// In continuous mode, windows are redrawn every frame instead, which suits games and visualisations.
+///
+/// Schedulers are cheap to clone, and every clone refers to the same scheduler,
+/// so they can be moved into event handlers, or to other threads.
+#[derive(Clone, Default)]
+pub struct FrameScheduler {
+    inner: Arc<SchedulerInner>,
+}
+
+#[derive(Default)]
+struct SchedulerInner {
+    /// Whether something was invalidated since the last frame.
+    invalidated: AtomicBool,
+    continuous: AtomicBool,
+    /// Wakes the app's event loop up, so it notices invalidations made while it sleeps.
+    waker: Mutex<Option<Box<dyn Fn() + Send>>>,
+}
+
+impl FrameScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests a redraw of every window before the next frame.
+    pub fn invalidate(&self) {
+        // Only the first invalidation since the last frame needs to wake the event loop up.
+        if !self.inner.invalidated.swap(true, Ordering::AcqRel) {
+            self.wake();
+        }
+    }
+
+    /// Whether something was invalidated since the last frame.
+    pub fn is_invalidated(&self) -> bool {
+        self.inner.invalidated.load(Ordering::Acquire)
+    }
+
+    /// Turns continuous mode on or off. In continuous mode, windows are redrawn every frame, whether invalidated or not.
+    pub fn set_continuous(&self, continuous: bool) {
+        self.inner.continuous.store(continuous, Ordering::Release);
+        if continuous {
+            self.wake();
+        }
+    }
+
+    pub fn is_continuous(&self) -> bool {
+        self.inner.continuous.load(Ordering::Acquire)
+    }
+
+    /// Whether the windows should be redrawn this frame. Clears the invalidation.
+    pub(crate) fn take_frame(&self) -> bool {
+        let invalidated = self.inner.invalidated.swap(false, Ordering::AcqRel);
+        invalidated || self.is_continuous()
+    }
+
+    /// Sets the function that wakes the event loop up.
+    pub(crate) fn set_waker<F: Fn() + Send + 'static>(&self, waker: F) {
+        *self.inner.waker.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(Box::new(waker));
+    }
+
+    fn wake(&self) {
+        if let Some(waker) = self.inner.waker.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).as_ref() {
+            waker();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+
+    fn counting_waker(scheduler: &FrameScheduler) -> Arc<AtomicUsize> {
+        let wakes = Arc::new(AtomicUsize::new(0));
+        let counter = wakes.clone();
+        scheduler.set_waker(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        wakes
+    }
+
+    #[test]
+    fn many_invalidations_wake_the_event_loop_once() {
+        let scheduler = FrameScheduler::new();
+        let wakes = counting_waker(&scheduler);
+
+        for _ in 0..10 {
+            scheduler.clone().invalidate();
+        }
+        assert_eq!(wakes.load(Ordering::SeqCst), 1);
+
+        // Once the frame is taken, the next invalidation has to wake it up again.
+        assert!(scheduler.take_frame());
+        scheduler.invalidate();
+        assert_eq!(wakes.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn taking_a_frame_clears_the_invalidation() {
+        let scheduler = FrameScheduler::new();
+        assert!(!scheduler.take_frame());
+
+        scheduler.invalidate();
+        assert!(scheduler.take_frame());
+        assert!(!scheduler.is_invalidated());
+        assert!(!scheduler.take_frame());
+    }
+
+    #[test]
+    fn continuous_mode_draws_every_frame_but_still_clears_the_invalidation() {
+        let scheduler = FrameScheduler::new();
+        scheduler.set_continuous(true);
+        scheduler.invalidate();
+
+        assert!(scheduler.take_frame());
+        assert!(!scheduler.is_invalidated());
+        assert!(scheduler.take_frame());
+
+        scheduler.set_continuous(false);
+        assert!(!scheduler.take_frame());
+    }
+}
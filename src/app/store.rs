@@ -0,0 +1,134 @@
+use futures_signals::signal::{Mutable, MutableSignalCloned, MutableSignalRef, Signal};
+
+use super::scheduler::FrameScheduler;
+
+/// A typed change to the app's state, sent to a [`Store`] with [`Store::dispatch`].
+pub trait Action<TState> {
+    fn apply(self, state: &mut TState);
+}
+
+/// Container for the app's state.
+/// Every change made through it notifies the signals derived from it, so the UI can rerender,
+/// and invalidates the app's windows, so they are redrawn.
+///
+/// Stores are cheap to clone, and every clone refers to the same state,
+/// so they can be moved into event handlers and components.
+pub struct Store<TState> {
+    state: Mutable<TState>,
+    scheduler: Option<FrameScheduler>,
+}
+
+impl<TState> Clone for Store<TState> {
+    fn clone(&self) -> Self {
+        Self { state: self.state.clone(), scheduler: self.scheduler.clone() }
+    }
+}
+
+impl<TState: std::fmt::Debug> std::fmt::Debug for Store<TState> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Store").field("state", &self.state).finish_non_exhaustive()
+    }
+}
+
+impl<TState> Store<TState> {
+    pub fn new(initial_state: TState) -> Self {
+        Self { state: Mutable::new(initial_state), scheduler: None }
+    }
+
+    /// Creates a store whose changes invalidate the windows of `scheduler`'s app.
+    pub fn with_scheduler(initial_state: TState, scheduler: FrameScheduler) -> Self {
+        Self { state: Mutable::new(initial_state), scheduler: Some(scheduler) }
+    }
+
+    /// Applies an action to the state, then notifies subscribed signals.
+    pub fn dispatch<A: Action<TState>>(&self, action: A) {
+        action.apply(&mut self.state.lock_mut());
+        self.invalidate();
+    }
+
+    /// Mutates the state in place, then notifies subscribed signals.
+    /// Prefer [`Store::dispatch`] for changes that have a name.
+    pub fn update<F: FnOnce(&mut TState)>(&self, f: F) {
+        f(&mut self.state.lock_mut());
+        self.invalidate();
+    }
+
+    fn invalidate(&self) {
+        if let Some(scheduler) = &self.scheduler {
+            scheduler.invalidate();
+        }
+    }
+
+    /// Reads the state without subscribing to it.
+    pub fn read<B, F: FnOnce(&TState) -> B>(&self, f: F) -> B {
+        f(&self.state.lock_ref())
+    }
+
+    /// Returns a signal of some value derived from the state, which changes whenever the state does.
+    pub fn signal_ref<B, F: FnMut(&TState) -> B>(&self, f: F) -> MutableSignalRef<TState, F> {
+        self.state.signal_ref(f)
+    }
+
+    /// The underlying mutable, for use with the rest of `futures_signals`.
+    pub fn mutable(&self) -> &Mutable<TState> {
+        &self.state
+    }
+}
+
+impl<TState: Clone> Store<TState> {
+    pub fn get_cloned(&self) -> TState {
+        self.state.get_cloned()
+    }
+
+    pub fn signal_cloned(&self) -> MutableSignalCloned<TState> {
+        self.state.signal_cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{pin::Pin, task::{Context, Poll}};
+
+    use futures::task::noop_waker_ref;
+
+    use super::*;
+
+    struct Increment(usize);
+
+    impl Action<usize> for Increment {
+        fn apply(self, state: &mut usize) {
+            *state += self.0;
+        }
+    }
+
+    fn poll<S: Signal + Unpin>(signal: &mut S) -> Poll<Option<S::Item>> {
+        Pin::new(signal).poll_change(&mut Context::from_waker(noop_waker_ref()))
+    }
+
+    #[test]
+    fn dispatching_updates_subscribers_and_invalidates_the_scheduler() {
+        let scheduler = FrameScheduler::new();
+        let store = Store::with_scheduler(1, scheduler.clone());
+        let mut doubled = store.signal_ref(|count| count * 2);
+        assert_eq!(poll(&mut doubled), Poll::Ready(Some(2)));
+        assert_eq!(poll(&mut doubled), Poll::Pending);
+        assert!(!scheduler.is_invalidated());
+
+        store.dispatch(Increment(2));
+        assert_eq!(poll(&mut doubled), Poll::Ready(Some(6)));
+        assert!(scheduler.is_invalidated());
+    }
+
+    #[test]
+    fn updating_updates_subscribers_and_invalidates_the_scheduler() {
+        let scheduler = FrameScheduler::new();
+        let store = Store::with_scheduler(1, scheduler.clone());
+        let mut doubled = store.signal_ref(|count| count * 2);
+        assert_eq!(poll(&mut doubled), Poll::Ready(Some(2)));
+
+        store.update(|count| *count = 5);
+        assert_eq!(poll(&mut doubled), Poll::Ready(Some(10)));
+        assert!(scheduler.is_invalidated());
+        assert_eq!(store.read(|count| *count), 5);
+    }
+}
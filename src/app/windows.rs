@@ -0,0 +1,196 @@
+use winit::{
+    event_loop::{EventLoopProxy, EventLoopWindowTarget},
+    window::{CursorIcon, Fullscreen, Window, WindowId},
+};
+
+use crate::{error::UIError, renderer::engine::render_engine::RenderingEngine};
+
+/// Descriptor for opening a window.
+#[derive(Debug, Clone)]
+pub struct WindowDescriptor {
+    pub title: String,
+    /// Size of the window's contents, in logical pixels.
+    pub size: (i32, i32),
+}
+
+impl Default for WindowDescriptor {
+    fn default() -> Self {
+        Self {
+            title: "Untitled Window".to_owned(),
+            size: (640, 360),
+        }
+    }
+}
+
+impl WindowDescriptor {
+    pub(crate) fn build<T>(&self, target: &EventLoopWindowTarget<T>) -> Result<Window, UIError> {
+        let window = winit::window::WindowBuilder::new()
+            .with_title(self.title.clone())
+            .with_inner_size(winit::dpi::LogicalSize {
+                width: self.size.0,
+                height: self.size.1,
+            })
+            .with_visible(false)
+            .build(target)?;
+        Ok(window)
+    }
+}
+
+/// Sets up the rendering engine of a window that was just opened, adding its render modules and input handlers.
+/// If it fails, the window is closed and the error goes to the app's error hook.
+pub type WindowSetup = Box<dyn FnOnce(&mut RenderingEngine) -> Result<(), UIError> + Send>;
+
+/// Requests sent to a running app's event loop.
+pub(crate) enum AppEvent {
+    OpenWindow(WindowDescriptor, WindowSetup),
+    CloseWindow(WindowId),
+    WindowCommand(WindowId, WindowCommand),
+    /// Something was invalidated, so the event loop must wake up to redraw.
+    Wake,
+}
+
+/// A change to an open window. Sizes are in logical pixels.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WindowCommand {
+    SetTitle(String),
+    SetSize((i32, i32)),
+    SetMinSize(Option<(i32, i32)>),
+    SetMaxSize(Option<(i32, i32)>),
+    /// Makes the window borderless fullscreen on its current monitor, or windowed again.
+    SetFullscreen(bool),
+    SetResizable(bool),
+    SetDecorations(bool),
+    SetCursorIcon(CursorIcon),
+    SetCursorVisible(bool),
+    SetVisible(bool),
+}
+
+impl WindowCommand {
+    pub(crate) fn apply(self, window: &Window) {
+        let logical = |(width, height): (i32, i32)| winit::dpi::LogicalSize { width, height };
+
+        match self {
+            WindowCommand::SetTitle(title) => window.set_title(&title),
+            WindowCommand::SetSize(size) => window.set_inner_size(logical(size)),
+            WindowCommand::SetMinSize(size) => window.set_min_inner_size(size.map(logical)),
+            WindowCommand::SetMaxSize(size) => window.set_max_inner_size(size.map(logical)),
+            WindowCommand::SetFullscreen(fullscreen) =>
+                window.set_fullscreen(fullscreen.then_some(Fullscreen::Borderless(None))),
+            WindowCommand::SetResizable(resizable) => window.set_resizable(resizable),
+            WindowCommand::SetDecorations(decorations) => window.set_decorations(decorations),
+            WindowCommand::SetCursorIcon(icon) => window.set_cursor_icon(icon),
+            WindowCommand::SetCursorVisible(visible) => window.set_cursor_visible(visible),
+            WindowCommand::SetVisible(visible) => window.set_visible(visible),
+        }
+    }
+}
+
+/// Opens and closes the windows of an app while it's running.
+///
+/// Managers are cheap to clone and can be moved into event handlers, or to other threads.
+/// Requests are handled by the app's event loop, so they take effect shortly after being made.
+#[derive(Clone)]
+pub struct WindowManager {
+    pub(crate) proxy: EventLoopProxy<AppEvent>,
+}
+
+impl WindowManager {
+    /// Opens a new window, which renders with the same device as the app's other windows.
+    /// Once it's open, `setup` is called with its rendering engine, where its render modules and input handlers can be added.
+    pub fn open_window<F>(&self, descriptor: WindowDescriptor, setup: F) -> Result<(), UIError>
+    where
+        F: FnOnce(&mut RenderingEngine) -> Result<(), UIError> + Send + 'static,
+    {
+        self.send(AppEvent::OpenWindow(descriptor, Box::new(setup)))
+    }
+
+    /// Closes a window. Closing the app's main window quits the app.
+    pub fn close_window(&self, id: WindowId) -> Result<(), UIError> {
+        self.send(AppEvent::CloseWindow(id))
+    }
+
+    /// Returns a handle for changing a window while the app runs.
+    pub fn window(&self, id: WindowId) -> WindowHandle {
+        WindowHandle { id, proxy: self.proxy.clone() }
+    }
+
+    fn send(&self, event: AppEvent) -> Result<(), UIError> {
+        send(&self.proxy, event)
+    }
+}
+
+/// Changes an open window while the app runs, like its title, size or cursor.
+///
+/// Handles are cheap to clone and can be moved into event handlers, or to other threads.
+/// Changes are applied by the app's event loop, so they take effect shortly after being requested.
+/// Changes to a window that was closed are ignored.
+#[derive(Clone)]
+pub struct WindowHandle {
+    id: WindowId,
+    proxy: EventLoopProxy<AppEvent>,
+}
+
+impl WindowHandle {
+    pub fn id(&self) -> WindowId {
+        self.id
+    }
+
+    pub fn set_title<S: Into<String>>(&self, title: S) -> Result<(), UIError> {
+        self.command(WindowCommand::SetTitle(title.into()))
+    }
+
+    /// Resizes the window's contents, in logical pixels.
+    pub fn set_size(&self, size: (i32, i32)) -> Result<(), UIError> {
+        self.command(WindowCommand::SetSize(size))
+    }
+
+    /// Sets the smallest size the user can resize the window to, in logical pixels.
+    pub fn set_min_size(&self, size: Option<(i32, i32)>) -> Result<(), UIError> {
+        self.command(WindowCommand::SetMinSize(size))
+    }
+
+    /// Sets the largest size the user can resize the window to, in logical pixels.
+    pub fn set_max_size(&self, size: Option<(i32, i32)>) -> Result<(), UIError> {
+        self.command(WindowCommand::SetMaxSize(size))
+    }
+
+    pub fn set_fullscreen(&self, fullscreen: bool) -> Result<(), UIError> {
+        self.command(WindowCommand::SetFullscreen(fullscreen))
+    }
+
+    pub fn set_resizable(&self, resizable: bool) -> Result<(), UIError> {
+        self.command(WindowCommand::SetResizable(resizable))
+    }
+
+    /// Shows or hides the title bar and borders of the window.
+    pub fn set_decorations(&self, decorations: bool) -> Result<(), UIError> {
+        self.command(WindowCommand::SetDecorations(decorations))
+    }
+
+    pub fn set_cursor_icon(&self, icon: CursorIcon) -> Result<(), UIError> {
+        self.command(WindowCommand::SetCursorIcon(icon))
+    }
+
+    pub fn set_cursor_visible(&self, visible: bool) -> Result<(), UIError> {
+        self.command(WindowCommand::SetCursorVisible(visible))
+    }
+
+    pub fn set_visible(&self, visible: bool) -> Result<(), UIError> {
+        self.command(WindowCommand::SetVisible(visible))
+    }
+
+    /// Closes the window. Closing the app's main window quits the app.
+    pub fn close(&self) -> Result<(), UIError> {
+        send(&self.proxy, AppEvent::CloseWindow(self.id))
+    }
+
+    pub fn command(&self, command: WindowCommand) -> Result<(), UIError> {
+        send(&self.proxy, AppEvent::WindowCommand(self.id, command))
+    }
+}
+
+fn send(proxy: &EventLoopProxy<AppEvent>, event: AppEvent) -> Result<(), UIError> {
+    proxy
+        .send_event(event)
+        .map_err(|_| UIError::AppNotRunning)
+}
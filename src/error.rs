@@ -0,0 +1,233 @@
+use std::{error::Error, fmt, ops::Range, path::PathBuf};
+
+use crate::renderer::engine::render_engine::RenderModuleHandle;
+
+/// Everything that can go wrong in an app, or while rendering.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum UIError {
+    /// The platform couldn't create a window.
+    WindowCreation(winit::error::OsError),
+    /// A window's surface couldn't be created.
+    SurfaceCreation(wgpu::CreateSurfaceError),
+    /// The adapter shared by the app's windows can't present to a new window.
+    IncompatibleSurface,
+    /// No adapter matches the requested options.
+    NoAdapter {
+        backends: wgpu::Backends,
+        power_preference: wgpu::PowerPreference,
+        force_fallback_adapter: bool,
+    },
+    /// The adapter couldn't provide a device.
+    DeviceRequest(wgpu::RequestDeviceError),
+    /// A window's surface was lost, and couldn't be configured again.
+    SurfaceLost,
+    /// Acquiring a frame from a window's surface failed for another reason.
+    Surface(wgpu::SurfaceError),
+    /// The GPU (or the system) ran out of memory.
+    OutOfMemory,
+    /// The device was lost, so it must be replaced before rendering again.
+    DeviceLost,
+    /// The GPU reported an error on its own, outside of any call that could return it.
+    Gpu(String),
+    /// A shader (or a pipeline using it) failed validation.
+    Shader(String),
+    /// A font file couldn't be read.
+    FontFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// Font data didn't contain any font.
+    InvalidFont,
+    /// Render targets must have a non-zero size.
+    InvalidTargetSize(u32, u32),
+    /// A frame couldn't be read back from the GPU.
+    Readback(String),
+    /// Reading or writing a file failed.
+    Io(std::io::Error),
+    /// An image couldn't be encoded or decoded as PNG.
+    #[cfg(feature = "snapshot")]
+    Png(String),
+    /// A dock arrangement couldn't be written, or read back.
+    Arrangement(String),
+    /// A snapshot has no golden image to be compared against, and updating wasn't requested.
+    #[cfg(feature = "snapshot")]
+    SnapshotMissing {
+        name: String,
+        golden_path: PathBuf,
+    },
+    /// A snapshot didn't match its golden image.
+    #[cfg(feature = "snapshot")]
+    SnapshotMismatch {
+        name: String,
+        differing_pixels: usize,
+        diff_path: PathBuf,
+    },
+    /// A component tried to rerender primitives outside of the range it owns, so the rerender was dropped.
+    InvalidRerender {
+        owned: Range<usize>,
+        range: Range<usize>,
+    },
+    /// Primitives can't be spliced into a range that ends before it starts.
+    InvalidSplice(Range<usize>),
+    /// The app's event loop isn't running anymore, so it can't receive requests.
+    AppNotRunning,
+    /// A render module failed for a reason of its own.
+    RenderModule(Box<dyn Error + Send + Sync>),
+    /// Some render modules failed while rendering a frame. The others were rendered anyway.
+    RenderModules(Vec<(RenderModuleHandle, UIError)>),
+}
+
+impl UIError {
+    /// Wraps an error of a render module.
+    pub fn render_module<E: Into<Box<dyn Error + Send + Sync>>>(error: E) -> Self {
+        UIError::RenderModule(error.into())
+    }
+}
+
+impl fmt::Display for UIError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UIError::WindowCreation(e) => write!(f, "Couldn't create a window: {}", e),
+            UIError::SurfaceCreation(e) => write!(f, "Couldn't create a surface for a window: {}", e),
+            UIError::IncompatibleSurface => write!(f, "The shared adapter can't present to this window."),
+            UIError::NoAdapter { backends, power_preference, force_fallback_adapter } => write!(
+                f,
+                "No GPU adapter matches the requested options (backends: {:?}, power preference: {:?}, fallback adapter: {}).",
+                backends, power_preference, force_fallback_adapter
+            ),
+            UIError::DeviceRequest(e) => write!(f, "Couldn't get a device from the adapter: {}", e),
+            UIError::SurfaceLost => write!(f, "A window's surface was lost."),
+            UIError::Surface(e) => write!(f, "Couldn't get a frame from a window's surface: {}", e),
+            UIError::OutOfMemory => write!(f, "Out of memory."),
+            UIError::DeviceLost => write!(f, "The GPU device was lost."),
+            UIError::Gpu(message) => write!(f, "GPU error: {}", message),
+            UIError::Shader(message) => write!(f, "Invalid shader: {}", message),
+            UIError::FontFile { path, source } => write!(f, "Couldn't read font file {}: {}", path.display(), source),
+            UIError::InvalidFont => write!(f, "The font data doesn't contain any font."),
+            UIError::InvalidTargetSize(width, height) =>
+                write!(f, "Render targets must have a non-zero size, but got {}x{}.", width, height),
+            UIError::Readback(message) => write!(f, "Couldn't read the frame back: {}", message),
+            UIError::Io(e) => write!(f, "{}", e),
+            #[cfg(feature = "snapshot")]
+            UIError::Png(message) => write!(f, "PNG error: {}", message),
+            UIError::Arrangement(message) => write!(f, "Invalid dock arrangement: {}", message),
+            #[cfg(feature = "snapshot")]
+            UIError::SnapshotMissing { name, golden_path } => write!(
+                f,
+                "Snapshot '{}' has no golden image at {}. Run with {} set to write it.",
+                name,
+                golden_path.display(),
+                crate::renderer::snapshot::UPDATE_SNAPSHOTS_VAR
+            ),
+            #[cfg(feature = "snapshot")]
+            UIError::SnapshotMismatch { name, differing_pixels, diff_path } => write!(
+                f,
+                "Snapshot '{}' differs from its golden image in {} pixels, see {}.",
+                name,
+                differing_pixels,
+                diff_path.display()
+            ),
+            UIError::InvalidRerender { owned, range } => write!(
+                f,
+                "A component owning {:?} tried to rerender {:?}, which is outside of it.",
+                owned,
+                range
+            ),
+            UIError::InvalidSplice(range) => write!(f, "Can't splice primitives into {:?}, which ends before it starts.", range),
+            UIError::AppNotRunning => write!(f, "The app isn't running anymore."),
+            UIError::RenderModule(e) => write!(f, "A render module failed: {}", e),
+            UIError::RenderModules(errors) => {
+                write!(f, "{} render modules failed:", errors.len())?;
+                for (_, error) in errors {
+                    write!(f, " {}", error)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for UIError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            UIError::WindowCreation(e) => Some(e),
+            UIError::SurfaceCreation(e) => Some(e),
+            UIError::DeviceRequest(e) => Some(e),
+            UIError::Surface(e) => Some(e),
+            UIError::FontFile { source, .. } => Some(source),
+            UIError::Io(e) => Some(e),
+            UIError::RenderModule(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<winit::error::OsError> for UIError {
+    fn from(e: winit::error::OsError) -> Self {
+        UIError::WindowCreation(e)
+    }
+}
+
+impl From<wgpu::CreateSurfaceError> for UIError {
+    fn from(e: wgpu::CreateSurfaceError) -> Self {
+        UIError::SurfaceCreation(e)
+    }
+}
+
+impl From<wgpu::RequestDeviceError> for UIError {
+    fn from(e: wgpu::RequestDeviceError) -> Self {
+        UIError::DeviceRequest(e)
+    }
+}
+
+impl From<wgpu::SurfaceError> for UIError {
+    fn from(e: wgpu::SurfaceError) -> Self {
+        match e {
+            wgpu::SurfaceError::Lost => UIError::SurfaceLost,
+            wgpu::SurfaceError::OutOfMemory => UIError::OutOfMemory,
+            e => UIError::Surface(e),
+        }
+    }
+}
+
+impl From<std::io::Error> for UIError {
+    fn from(e: std::io::Error) -> Self {
+        UIError::Io(e)
+    }
+}
+
+#[cfg(feature = "snapshot")]
+impl From<png::EncodingError> for UIError {
+    fn from(e: png::EncodingError) -> Self {
+        UIError::Png(e.to_string())
+    }
+}
+
+#[cfg(feature = "snapshot")]
+impl From<png::DecodingError> for UIError {
+    fn from(e: png::DecodingError) -> Self {
+        UIError::Png(e.to_string())
+    }
+}
+
+/// What an app does after reporting an error to its error hook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorResponse {
+    /// Keeps running, for errors the app recovered from (or can live with).
+    Continue,
+    /// Quits the app.
+    Exit,
+}
+
+/// Receives the errors that happen while an app runs, deciding whether it keeps running.
+pub type ErrorHook = Box<dyn FnMut(&UIError) -> ErrorResponse>;
+
+/// The error hook apps start with: prints every error, and quits when out of memory.
+pub fn default_error_hook(error: &UIError) -> ErrorResponse {
+    eprintln!("{}", error);
+    match error {
+        UIError::OutOfMemory => ErrorResponse::Exit,
+        _ => ErrorResponse::Continue,
+    }
+}
@@ -3,4 +3,5 @@
 pub mod renderer;
 pub mod ui;
 pub mod app;
+pub mod error;
 pub mod prelude;
\ No newline at end of file
@@ -1,3 +1,5 @@
 pub use crate::app::app_builder::UIAppBuilder;
 pub use crate::app::UIApp;
+pub use crate::app::store::{Action, Store};
+pub use crate::error::{ErrorResponse, UIError};
 pub use futures_signals as signals;
\ No newline at end of file
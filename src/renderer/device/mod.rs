@@ -1,18 +1,48 @@
-use std::error::Error;
+use std::future::Future;
+
+use crate::error::UIError;
+
+/// Options for choosing the GPU an engine renders with, and how it presents to windows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GPUOptions {
+    /// Graphics APIs the adapter may use.
+    pub backends: wgpu::Backends,
+    /// Whether to prefer an integrated (low power) or a discrete (high performance) GPU.
+    pub power_preference: wgpu::PowerPreference,
+    /// Whether to only accept software (fallback) adapters.
+    pub force_fallback_adapter: bool,
+    /// How frames are presented to windows, which decides vsync.
+    /// If a window's surface doesn't support it, the first mode the surface supports is used.
+    pub present_mode: wgpu::PresentMode,
+    /// How windows are composited with what's behind them.
+    /// If a window's surface doesn't support it, the first mode the surface supports is used.
+    pub alpha_mode: wgpu::CompositeAlphaMode,
+}
+
+impl Default for GPUOptions {
+    fn default() -> Self {
+        Self {
+            backends: wgpu::Backends::all(),
+            power_preference: wgpu::PowerPreference::default(),
+            force_fallback_adapter: false,
+            present_mode: wgpu::PresentMode::AutoVsync,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+        }
+    }
+}
 
 /// Retrieves a wgpu instance.
-pub fn create_instance() -> wgpu::Instance {
-    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-        backends: wgpu::Backends::all(),
+pub fn create_instance(options: &GPUOptions) -> wgpu::Instance {
+    wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: options.backends,
         ..Default::default()
-    });
-    instance
+    })
 }
 
 /// Returns a default wgpu device.
 pub async fn get_device(
     adapter: &wgpu::Adapter,
-) -> Result<(wgpu::Device, wgpu::Queue), Box<dyn Error>> {
+) -> Result<(wgpu::Device, wgpu::Queue), UIError> {
     let (device, queue) = adapter
         .request_device(
             &wgpu::DeviceDescriptor {
@@ -26,62 +56,200 @@ pub async fn get_device(
     Ok((device, queue))
 }
 
-/// Returns some random adapter.
-/// TODO: Allow the user to choose this themselves.
-pub async fn get_adapter(instance: wgpu::Instance, surface: &wgpu::Surface) -> wgpu::Adapter {
-    let adapter = instance
-        .request_adapter(&wgpu::RequestAdapterOptionsBase {
-            power_preference: wgpu::PowerPreference::default(),
-            force_fallback_adapter: false,
-            compatible_surface: Some(surface),
-        })
-        .await;
-    let adapter = match adapter {
-        Some(v) => v,
-        None => todo!(),
+/// Returns an adapter matching `options`, compatible with `surface` if there is one.
+/// The request starts right away, so the returned future doesn't borrow anything.
+pub fn get_adapter(
+    instance: &wgpu::Instance,
+    surface: Option<&wgpu::Surface>,
+    options: &GPUOptions,
+) -> impl Future<Output = Result<wgpu::Adapter, UIError>> + Send {
+    let adapter = instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: options.power_preference,
+        force_fallback_adapter: options.force_fallback_adapter,
+        compatible_surface: surface,
+    });
+    let error = UIError::NoAdapter {
+        backends: options.backends,
+        power_preference: options.power_preference,
+        force_fallback_adapter: options.force_fallback_adapter,
     };
-    adapter
+    async move { adapter.await.ok_or(error) }
 }
 
 /// Creates a new wgpu surface.
 pub fn create_surface(
     instance: &wgpu::Instance,
     window: &winit::window::Window,
-) -> Result<wgpu::Surface, Box<dyn Error>> {
+) -> Result<wgpu::Surface, UIError> {
     let surface = unsafe { instance.create_surface(window) }?;
     Ok(surface)
 }
 
-/// Gets a surface configuration.
-/// TODO: Allow the user to decide on their if they want.
+/// Gets a surface configuration, with the present and alpha modes from `options` if the surface supports them.
 pub fn get_default_surface_configuration(
     surface_format: wgpu::TextureFormat,
     window_size: winit::dpi::PhysicalSize<u32>,
     surface_capabilities: wgpu::SurfaceCapabilities,
+    options: &GPUOptions,
 ) -> wgpu::SurfaceConfiguration {
-    let config = wgpu::SurfaceConfiguration {
+    wgpu::SurfaceConfiguration {
         usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
         format: surface_format,
         // INFO: `width` and `height` can never be 0, otherwise the program
         // might crash unexpectedly.
         width: window_size.width,
         height: window_size.height,
-        // TODO: This will be choosable by the user futurely.
-        present_mode: surface_capabilities.present_modes[0],
-        alpha_mode: surface_capabilities.alpha_modes[0],
+        present_mode: choose_present_mode(options.present_mode, &surface_capabilities.present_modes),
+        alpha_mode: choose_alpha_mode(options.alpha_mode, &surface_capabilities.alpha_modes),
         view_formats: Vec::new(),
+    }
+}
+
+/// The automatic modes are always supported, since wgpu picks a supported mode for them.
+fn choose_present_mode(requested: wgpu::PresentMode, supported: &[wgpu::PresentMode]) -> wgpu::PresentMode {
+    match requested {
+        wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync => requested,
+        _ if supported.contains(&requested) => requested,
+        _ => supported[0],
+    }
+}
+
+fn choose_alpha_mode(requested: wgpu::CompositeAlphaMode, supported: &[wgpu::CompositeAlphaMode]) -> wgpu::CompositeAlphaMode {
+    match requested {
+        wgpu::CompositeAlphaMode::Auto => requested,
+        _ if supported.contains(&requested) => requested,
+        _ => supported[0],
+    }
+}
+
+/// Gets the configuration describing an offscreen render target.
+/// There is no surface to present to, so the present and alpha modes are placeholders.
+pub fn get_offscreen_configuration(
+    format: wgpu::TextureFormat,
+    size: winit::dpi::PhysicalSize<u32>,
+) -> wgpu::SurfaceConfiguration {
+    wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        format,
+        width: size.width,
+        height: size.height,
+        present_mode: wgpu::PresentMode::Fifo,
+        alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+        view_formats: Vec::new(),
+    }
+}
+
+/// Creates the texture an offscreen render target renders to.
+pub fn create_offscreen_texture(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Offscreen Render Target"),
+        size: wgpu::Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: config.format,
+        view_formats: &[],
+        usage: config.usage,
+    })
+}
+
+/// Copies a texture back to the CPU as tightly packed RGBA8 bytes.
+/// Only 8-bit RGBA and BGRA textures are supported.
+pub async fn read_texture_rgba(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+) -> Result<Vec<u8>, UIError> {
+    let is_bgra = match texture.format() {
+        wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+        other => return Err(UIError::Readback(format!("Can't read back textures of format {:?}.", other))),
     };
-    config
+
+    let (width, height) = (texture.width(), texture.height());
+    let unpadded_bytes_per_row = width * 4;
+    // Rows copied into buffers must be aligned, so there might be some padding at their end.
+    let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+        * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+    let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback Buffer"),
+        size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut cmd_encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Readback Encoder"),
+    });
+    cmd_encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &readback_buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(height),
+            },
+        },
+        texture.size(),
+    );
+    queue.submit(std::iter::once(cmd_encoder.finish()));
+
+    let buffer_slice = readback_buffer.slice(..);
+    let (sender, receiver) = futures::channel::oneshot::channel();
+    buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    receiver
+        .await
+        .map_err(|_| UIError::Readback("The device was dropped while reading back.".to_owned()))?
+        .map_err(|e| UIError::Readback(e.to_string()))?;
+
+    let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+    {
+        let padded_pixels = buffer_slice.get_mapped_range();
+        for row in padded_pixels.chunks(padded_bytes_per_row as usize) {
+            pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+        }
+    }
+    readback_buffer.unmap();
+
+    if is_bgra {
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+
+    Ok(pixels)
+}
+
+/// Runs `f`, which creates GPU resources, turning the validation errors it causes into a [`UIError::Shader`]
+/// instead of letting wgpu panic.
+pub fn with_validation<T>(device: &wgpu::Device, f: impl FnOnce() -> T) -> Result<T, UIError> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let value = f();
+    match futures::executor::block_on(device.pop_error_scope()) {
+        Some(wgpu::Error::Validation { description, .. }) => Err(UIError::Shader(description)),
+        Some(error) => Err(UIError::Shader(error.to_string())),
+        None => Ok(value),
+    }
 }
 
 /// Retrieves the swapchain format.
 pub fn get_surface_format(surface_capabilities: &wgpu::SurfaceCapabilities) -> wgpu::TextureFormat {
-    let surface_format = surface_capabilities
+    surface_capabilities
         .formats
         .iter()
         .copied()
-        .filter(|f| f.is_srgb())
-        .next()
-        .unwrap_or(surface_capabilities.formats[0]);
-    surface_format
+        .find(|f| f.is_srgb())
+        .unwrap_or(surface_capabilities.formats[0])
 }
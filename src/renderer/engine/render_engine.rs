@@ -1,73 +1,423 @@
-use std::error::Error;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+};
 
 use wgpu::{RenderPassDepthStencilAttachment, Texture, TextureUsages, TextureViewDescriptor};
 use winit::{
     event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
     event_loop::ControlFlow,
-    window::Window,
+    window::{Window, WindowId},
 };
 
-use crate::renderer::device::*;
+use crate::{error::UIError, renderer::device::*, ui::{input::InputRouter, locale::Locale}};
 
-use super::render_module::RenderModule;
+use super::render_module::{RenderModule, RenderPassMode};
 
 pub type SharedRenderModule = Box<dyn RenderModule>;
 
+/// Identifies a render module added to a [`RenderingEngine`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RenderModuleHandle(u64);
+
+/// A render module, along with what the engine needs to know to order it.
+struct RegisteredRenderModule {
+    handle: RenderModuleHandle,
+    /// Modules in lower layers are rendered first, so they end up below the ones in higher layers.
+    layer: i32,
+    module: SharedRenderModule,
+}
+
 /// Wrapper responsible for holding/handling the program's user interface primitives
 /// and broadcasting events to the underlying rendering API.
 pub struct RenderingEngine {
-    pub render_modules: Vec<SharedRenderModule>,
+    /// Modules in the order they're rendered in: by layer, then by when they were added.
+    render_modules: Vec<RegisteredRenderModule>,
+    next_render_module_handle: u64,
     pub gpu: RenderingEngineGPU,
+    /// Routes input events from the window to the fragments on the screen.
+    pub input: InputRouter,
+    /// Handed to every render module, see [`RenderModule::set_locale`].
+    locale: Locale,
+    /// The device replacing a lost one, while it's being requested. See [`RenderingEngine::poll_device_recovery`].
+    pending_gpu: Option<PendingGPU>,
 }
 
 pub struct RenderingEngineGPU {
-    pub device: wgpu::Device,
-    pub queue: wgpu::Queue,
-    pub surface: wgpu::Surface,
+    /// Shared with the engines of other windows, see [`SharedGPU`].
+    pub instance: Arc<wgpu::Instance>,
+    pub adapter: Arc<wgpu::Adapter>,
+    pub device: Arc<wgpu::Device>,
+    pub queue: Arc<wgpu::Queue>,
+    /// The options the adapter was chosen with, also used for configuring the surfaces of other windows.
+    pub options: GPUOptions,
+    /// Set when the device is lost, see [`RenderingEngine::recover_device`].
+    device_lost: Arc<AtomicBool>,
+    /// Errors the device reported on its own, see [`RenderingEngine::take_gpu_errors`].
+    uncaptured_errors: Arc<Mutex<Vec<UIError>>>,
+    pub target: RenderTarget,
+    /// Format and size of the render target.
+    /// For offscreen targets, this describes the texture instead of a surface.
     pub surface_config: wgpu::SurfaceConfiguration,
     pub depth_buffer: wgpu::Texture,
-    // Must be dropped *after* `self::surface`.
-    // Since the surface refers to it in spite of
-    // the borrow checker.
-    pub window: Window,
     pub window_size: winit::dpi::PhysicalSize<u32>,
 }
 
+impl RenderingEngineGPU {
+    /// The handles other engines need to render with the same device as this one.
+    pub fn shared(&self) -> SharedGPU {
+        SharedGPU {
+            instance: self.instance.clone(),
+            adapter: self.adapter.clone(),
+            device: self.device.clone(),
+            queue: self.queue.clone(),
+            options: self.options.clone(),
+            device_lost: self.device_lost.clone(),
+            uncaptured_errors: self.uncaptured_errors.clone(),
+        }
+    }
+
+    /// Whether the device was lost (or marked as lost), in which case nothing rendered with it will show up.
+    pub fn is_device_lost(&self) -> bool {
+        self.device_lost.load(Ordering::Acquire)
+    }
+}
+
+/// GPU handles that can be shared by several rendering engines (like the ones of an app's windows),
+/// so GPU resources created for one of them can be used by all of them.
+#[derive(Clone)]
+pub struct SharedGPU {
+    pub instance: Arc<wgpu::Instance>,
+    pub adapter: Arc<wgpu::Adapter>,
+    pub device: Arc<wgpu::Device>,
+    pub queue: Arc<wgpu::Queue>,
+    pub options: GPUOptions,
+    device_lost: Arc<AtomicBool>,
+    uncaptured_errors: Arc<Mutex<Vec<UIError>>>,
+}
+
+/// A device being requested, which doesn't borrow anything, so it can be kept while it's pending.
+type PendingGPU = Pin<Box<dyn Future<Output = Result<SharedGPU, UIError>> + Send>>;
+
+impl SharedGPU {
+    /// Requests an adapter and a device matching `options`, compatible with `surface` if there is one.
+    pub async fn request(
+        instance: Arc<wgpu::Instance>,
+        surface: Option<&wgpu::Surface>,
+        options: GPUOptions,
+    ) -> Result<Self, UIError> {
+        Self::request_pending(instance, surface, options).await
+    }
+
+    /// Like [`SharedGPU::request`], but `surface` is only borrowed while the request starts.
+    fn request_pending(instance: Arc<wgpu::Instance>, surface: Option<&wgpu::Surface>, options: GPUOptions) -> PendingGPU {
+        let adapter = get_adapter(&instance, surface, &options);
+
+        Box::pin(async move {
+            let adapter = adapter.await?;
+            let (device, queue) = get_device(&adapter).await?;
+
+            // wgpu 0.18 has no callback for lost devices, so they are noticed by the errors they cause.
+            // The other errors are queued for the app's error hook, instead of panicking like wgpu's default handler.
+            let device_lost = Arc::new(AtomicBool::new(false));
+            let uncaptured_errors = Arc::new(Mutex::new(Vec::new()));
+            let lost = device_lost.clone();
+            let queued = uncaptured_errors.clone();
+            device.on_uncaptured_error(Box::new(move |error| {
+                if is_device_lost_error(&error) {
+                    lost.store(true, Ordering::Release);
+                    return;
+                }
+
+                let error = match error {
+                    wgpu::Error::OutOfMemory { .. } => UIError::OutOfMemory,
+                    wgpu::Error::Validation { description, .. } => UIError::Gpu(description),
+                };
+                if let Ok(mut queued) = queued.lock() {
+                    queued.push(error);
+                }
+            }));
+
+            Ok(Self {
+                instance,
+                adapter: Arc::new(adapter),
+                device: Arc::new(device),
+                queue: Arc::new(queue),
+                options,
+                device_lost,
+                uncaptured_errors,
+            })
+        })
+    }
+}
+
+/// Whether an error was caused by the device being lost, looking for wgpu's own error in its sources,
+/// the way wgpu tells out of memory errors apart.
+fn is_device_lost_error(error: &wgpu::Error) -> bool {
+    let wgpu::Error::Validation { source, .. } = error else {
+        return false;
+    };
+
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(source.as_ref());
+    while let Some(error) = source {
+        if let Some(wgpu::core::device::DeviceError::Lost) = error.downcast_ref::<wgpu::core::device::DeviceError>() {
+            return true;
+        }
+        source = error.source();
+    }
+    false
+}
+
+/// Where the frames rendered by the engine end up.
+pub enum RenderTarget {
+    /// Renders to a window's surface, presenting every frame to the screen.
+    Window {
+        surface: wgpu::Surface,
+        // Must be dropped *after* `surface`.
+        // Since the surface refers to it in spite of
+        // the borrow checker.
+        window: Window,
+    },
+    /// Renders to an offscreen texture, whose contents can be read back.
+    Texture(wgpu::Texture),
+}
+
+/// Descriptor for creating a rendering engine without a window.
+pub struct HeadlessRenderingEngineDescriptor {
+    pub size: (u32, u32),
+    pub format: wgpu::TextureFormat,
+    /// Options for choosing the adapter. There is no surface, so the present and alpha modes are ignored.
+    pub options: GPUOptions,
+}
+
+impl Default for HeadlessRenderingEngineDescriptor {
+    fn default() -> Self {
+        Self {
+            size: (640, 360),
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            options: GPUOptions::default(),
+        }
+    }
+}
+
 impl RenderingEngine {
-    pub async fn new(window: Window) -> Result<Self, Box<dyn Error>> {
-        let window_size = window.inner_size();
-        let instance = create_instance();
+    /// Creates a new rendering engine for a window, choosing an adapter according to `options`.
+    pub async fn new(window: Window, options: GPUOptions) -> Result<Self, UIError> {
+        let instance = Arc::new(create_instance(&options));
         let surface = create_surface(&instance, &window)?;
-        let adapter = get_adapter(instance, &surface).await;
-        let (device, queue) = get_device(&adapter).await?;
-        let surface_capabilities = surface.get_capabilities(&adapter);
+        let shared = SharedGPU::request(instance, Some(&surface), options).await?;
+
+        Ok(Self::from_surface(window, surface, shared))
+    }
+
+    /// Creates a new rendering engine for a window, using the device of another engine.
+    /// The adapter of `shared` must be able to present to the window, which is usually the case on desktop platforms.
+    pub fn new_sharing(window: Window, shared: SharedGPU) -> Result<Self, UIError> {
+        let surface = create_surface(&shared.instance, &window)?;
+        if !shared.adapter.is_surface_supported(&surface) {
+            return Err(UIError::IncompatibleSurface);
+        }
+
+        Ok(Self::from_surface(window, surface, shared))
+    }
+
+    fn from_surface(window: Window, surface: wgpu::Surface, shared: SharedGPU) -> Self {
+        let window_size = window.inner_size();
+        let mut input = InputRouter::new();
+        input.set_scale_factor(window.scale_factor());
+        let surface_capabilities = surface.get_capabilities(&shared.adapter);
         // Assuming sRGB for now...
         let surface_format = get_surface_format(&surface_capabilities);
         let surface_config =
-            get_default_surface_configuration(surface_format, window_size, surface_capabilities);
+            get_default_surface_configuration(surface_format, window_size, surface_capabilities, &shared.options);
         let render_modules = Vec::new();
-        let depth_buffer = RenderingEngine::create_depth_texture(&device, &surface_config);
+        let depth_buffer = RenderingEngine::create_depth_texture(&shared.device, &surface_config);
+        surface.configure(&shared.device, &surface_config);
 
-        Ok(Self {
+        Self {
             gpu: RenderingEngineGPU {
-                window,
-                surface,
-                device,
-                queue,
+                target: RenderTarget::Window { surface, window },
+                instance: shared.instance,
+                adapter: shared.adapter,
+                device: shared.device,
+                queue: shared.queue,
+                options: shared.options,
+                device_lost: shared.device_lost,
+                uncaptured_errors: shared.uncaptured_errors,
                 surface_config,
                 window_size,
                 depth_buffer,
             },
             render_modules,
+            next_render_module_handle: 0,
+            input,
+            locale: Locale::default(),
+            pending_gpu: None,
+        }
+    }
+
+    /// Creates a new rendering engine that renders to an offscreen texture instead of a window.
+    /// Useful for rendering where there is no display, like in CI or on a server.
+    pub async fn new_headless(descriptor: HeadlessRenderingEngineDescriptor) -> Result<Self, UIError> {
+        let window_size = winit::dpi::PhysicalSize::new(descriptor.size.0, descriptor.size.1);
+        if !(window_size.width > 0 && window_size.height > 0) {
+            return Err(UIError::InvalidTargetSize(window_size.width, window_size.height));
+        }
+
+        let instance = Arc::new(create_instance(&descriptor.options));
+        let shared = SharedGPU::request(instance, None, descriptor.options).await?;
+        let surface_config = get_offscreen_configuration(descriptor.format, window_size);
+        let texture = create_offscreen_texture(&shared.device, &surface_config);
+        let depth_buffer = RenderingEngine::create_depth_texture(&shared.device, &surface_config);
+
+        Ok(Self {
+            gpu: RenderingEngineGPU {
+                target: RenderTarget::Texture(texture),
+                instance: shared.instance,
+                adapter: shared.adapter,
+                device: shared.device,
+                queue: shared.queue,
+                options: shared.options,
+                device_lost: shared.device_lost,
+                uncaptured_errors: shared.uncaptured_errors,
+                surface_config,
+                window_size,
+                depth_buffer,
+            },
+            render_modules: Vec::new(),
+            next_render_module_handle: 0,
+            input: InputRouter::new(),
+            locale: Locale::default(),
+            pending_gpu: None,
         })
     }
 
-    pub fn window(&self) -> &Window {
-        &self.gpu.window
+    /// Returns the window this engine renders to, if it isn't headless.
+    pub fn window(&self) -> Option<&Window> {
+        match &self.gpu.target {
+            RenderTarget::Window { window, .. } => Some(window),
+            RenderTarget::Texture(_) => None,
+        }
+    }
+
+    /// Returns the id of the window this engine renders to, if it isn't headless.
+    pub fn window_id(&self) -> Option<WindowId> {
+        self.window().map(|window| window.id())
+    }
+
+    /// Whether the device was lost (or marked as lost), in which case nothing rendered with it will show up.
+    pub fn is_device_lost(&self) -> bool {
+        self.gpu.is_device_lost()
+    }
+
+    /// Marks the device as lost, so it's replaced before the next frame.
+    /// Useful for switching GPUs, or after a driver reset wgpu didn't notice.
+    pub fn mark_device_lost(&self) {
+        self.gpu.device_lost.store(true, Ordering::Release);
+    }
+
+    /// Takes the errors the GPU reported on its own since the last call, outside of any call that could return them,
+    /// like validation errors of commands. Engines sharing a device share these errors too.
+    /// Apps give them to their error hook, see [`crate::app::UIApp::set_error_hook`].
+    pub fn take_gpu_errors(&self) -> Vec<UIError> {
+        match self.gpu.uncaptured_errors.lock() {
+            Ok(mut errors) => std::mem::take(&mut *errors),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Replaces a lost device with a new one (from a new adapter matching the same options),
+    /// and makes every render module recreate its GPU resources with it.
+    ///
+    /// Engines sharing the lost device should be given the new one with [`RenderingEngine::use_shared_gpu`],
+    /// instead of recovering on their own.
+    pub async fn recover_device(&mut self) -> Result<(), UIError> {
+        let shared = self.request_replacement_gpu().await?;
+        self.use_shared_gpu(shared)
+    }
+
+    /// Like [`RenderingEngine::recover_device`], but without waiting for the new device:
+    /// the first call starts requesting it, and the call that finds it ready switches to it.
+    /// Keep calling it (like on every redraw) while it's pending.
+    pub fn poll_device_recovery(&mut self) -> Poll<Result<(), UIError>> {
+        if self.pending_gpu.is_none() {
+            self.pending_gpu = Some(self.request_replacement_gpu());
+        }
+        let Some(pending) = self.pending_gpu.as_mut() else {
+            return Poll::Pending;
+        };
+
+        // Native adapters and devices are ready as soon as they're requested, so nothing needs to wake this up.
+        let mut context = Context::from_waker(futures::task::noop_waker_ref());
+        match pending.as_mut().poll(&mut context) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(shared) => {
+                self.pending_gpu = None;
+                Poll::Ready(shared.and_then(|shared| self.use_shared_gpu(shared)))
+            }
+        }
+    }
+
+    fn request_replacement_gpu(&self) -> PendingGPU {
+        let surface = match &self.gpu.target {
+            RenderTarget::Window { surface, .. } => Some(surface),
+            RenderTarget::Texture(_) => None,
+        };
+        SharedGPU::request_pending(self.gpu.instance.clone(), surface, self.gpu.options.clone())
+    }
+
+    /// Switches this engine to another device, recreating the render target and every module's GPU resources with it.
+    pub fn use_shared_gpu(&mut self, shared: SharedGPU) -> Result<(), UIError> {
+        if let RenderTarget::Window { surface, .. } = &self.gpu.target {
+            if !shared.adapter.is_surface_supported(surface) {
+                return Err(UIError::IncompatibleSurface);
+            }
+            let surface_capabilities = surface.get_capabilities(&shared.adapter);
+            let surface_format = get_surface_format(&surface_capabilities);
+            self.gpu.surface_config = get_default_surface_configuration(
+                surface_format, self.gpu.window_size, surface_capabilities, &shared.options);
+        }
+
+        self.gpu.instance = shared.instance;
+        self.gpu.adapter = shared.adapter;
+        self.gpu.device = shared.device;
+        self.gpu.queue = shared.queue;
+        self.gpu.options = shared.options;
+        self.gpu.device_lost = shared.device_lost;
+        self.gpu.uncaptured_errors = shared.uncaptured_errors;
+
+        self.reconfigure_surface();
+        self.gpu.depth_buffer = RenderingEngine::create_depth_texture(&self.gpu.device, &self.gpu.surface_config);
+
+        let errors: Vec<_> = self.render_modules
+            .iter_mut()
+            .filter_map(|registered| {
+                registered.module
+                    .recreate_gpu_resources(&self.gpu)
+                    .err()
+                    .map(|e| (registered.handle, e))
+            })
+            .collect();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(UIError::RenderModules(errors))
+        }
     }
 
     pub fn reconfigure_surface(&mut self) {
-        self.gpu.surface.configure(&self.gpu.device, &self.gpu.surface_config)
+        match &self.gpu.target {
+            RenderTarget::Window { surface, .. } => surface.configure(&self.gpu.device, &self.gpu.surface_config),
+            RenderTarget::Texture(_) => {
+                self.gpu.target = RenderTarget::Texture(create_offscreen_texture(&self.gpu.device, &self.gpu.surface_config))
+            }
+        }
     }
 
     pub fn create_depth_texture(device: &wgpu::Device, surface_config: &wgpu::SurfaceConfiguration) -> wgpu::Texture {
@@ -87,6 +437,7 @@ impl RenderingEngine {
         })
     }
 
+    /// Resizes the render target, be it the window's surface or the offscreen texture.
     pub fn resize_window(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         if !(new_size.width > 0 && new_size.height > 0) {
             return;
@@ -101,19 +452,94 @@ impl RenderingEngine {
     }
 
     pub fn request_window_redraw(&mut self) {
-        self.gpu.window.request_redraw()
+        if let Some(window) = self.window() {
+            window.request_redraw()
+        }
+    }
+
+    /// Dispatches a window event to the fragments' input handlers.
+    /// Returns whether any handler received it, in which case whatever schedules redraws should be told.
+    pub fn handle_input(&mut self, event: &WindowEvent) -> bool {
+        self.input.handle_window_event(event)
+    }
+
+    pub fn locale(&self) -> &Locale {
+        &self.locale
     }
 
-    pub fn handle_input(&mut self, event: &WindowEvent, control_flow: &mut ControlFlow) -> bool {
-        match event {
-            _ => return false,
+    /// Changes the locale of this engine, and of every render module in it.
+    pub fn set_locale(&mut self, locale: Locale) {
+        for registered in self.render_modules.iter_mut() {
+            registered.module.set_locale(&locale);
         }
+        self.locale = locale;
+    }
+
+    /// Adds a render module to layer 0, on top of the modules already in it.
+    pub fn add_render_module(&mut self, render_module: SharedRenderModule) -> RenderModuleHandle {
+        self.add_render_module_to_layer(render_module, 0)
+    }
 
-        true
+    /// Adds a render module to a layer, on top of the modules already in it.
+    /// Layers are rendered from the lowest to the highest, each in its own render pass,
+    /// so nothing in a layer is hidden by the depth of the layers below it.
+    pub fn add_render_module_to_layer(&mut self, mut render_module: SharedRenderModule, layer: i32) -> RenderModuleHandle {
+        render_module.set_locale(&self.locale);
+        let handle = RenderModuleHandle(self.next_render_module_handle);
+        self.next_render_module_handle += 1;
+        self.insert_render_module(RegisteredRenderModule { handle, layer, module: render_module });
+        handle
     }
 
-    pub fn add_render_module(&mut self, render_module: SharedRenderModule) {
-        self.render_modules.push(render_module);
+    /// Removes a render module, returning it.
+    pub fn remove_render_module(&mut self, handle: RenderModuleHandle) -> Option<SharedRenderModule> {
+        let index = self.render_module_index(handle)?;
+        Some(self.render_modules.remove(index).module)
+    }
+
+    /// Puts a render module in the place of another, returning the one that was replaced.
+    /// The new module keeps the handle and the layer of the old one.
+    pub fn replace_render_module(
+        &mut self,
+        handle: RenderModuleHandle,
+        mut render_module: SharedRenderModule,
+    ) -> Option<SharedRenderModule> {
+        let index = self.render_module_index(handle)?;
+        render_module.set_locale(&self.locale);
+        Some(std::mem::replace(&mut self.render_modules[index].module, render_module))
+    }
+
+    /// Moves a render module to another layer, on top of the modules already in it.
+    pub fn set_render_module_layer(&mut self, handle: RenderModuleHandle, layer: i32) {
+        if let Some(index) = self.render_module_index(handle) {
+            let mut registered = self.render_modules.remove(index);
+            registered.layer = layer;
+            self.insert_render_module(registered);
+        }
+    }
+
+    pub fn render_module(&self, handle: RenderModuleHandle) -> Option<&dyn RenderModule> {
+        let index = self.render_module_index(handle)?;
+        Some(self.render_modules[index].module.as_ref())
+    }
+
+    pub fn render_module_mut(&mut self, handle: RenderModuleHandle) -> Option<&mut (dyn RenderModule + 'static)> {
+        let index = self.render_module_index(handle)?;
+        Some(self.render_modules[index].module.as_mut())
+    }
+
+    /// Handles of every render module, in the order they're rendered in.
+    pub fn render_module_handles(&self) -> Vec<RenderModuleHandle> {
+        self.render_modules.iter().map(|registered| registered.handle).collect()
+    }
+
+    fn insert_render_module(&mut self, registered: RegisteredRenderModule) {
+        let index = self.render_modules.partition_point(|other| other.layer <= registered.layer);
+        self.render_modules.insert(index, registered);
+    }
+
+    fn render_module_index(&self, handle: RenderModuleHandle) -> Option<usize> {
+        self.render_modules.iter().position(|registered| registered.handle == handle)
     }
 
     /** Updates the engine state and rerenders it to screen. */
@@ -121,68 +547,166 @@ impl RenderingEngine {
         let _ = self.render();
     }
 
-    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        // Render to the current texture.
-        let render_target = self.gpu.surface.get_current_texture()?;
-        let main_texture_view = render_target
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
-        let depth_texture_view = self.gpu.depth_buffer.create_view(&wgpu::TextureViewDescriptor::default());
+    /// Renders a frame.
+    /// If some modules fail, the others are still rendered, and the frame is presented before their errors are returned.
+    pub fn render(&mut self) -> Result<(), UIError> {
+        if self.is_device_lost() {
+            return Err(UIError::DeviceLost);
+        }
+
+        let errors = match &self.gpu.target {
+            RenderTarget::Window { surface, .. } => {
+                // Render to the current texture.
+                let render_target = surface.get_current_texture()?;
+                let main_texture_view = render_target
+                    .texture
+                    .create_view(&wgpu::TextureViewDescriptor::default());
+
+                let errors = Self::render_modules_to_view(&mut self.render_modules, &self.gpu, &main_texture_view);
+
+                // Present the final result to the screen.
+                // TODO: Maybe in case of partial rendering it won't present to the screen.
+                render_target.present();
+                errors
+            }
+            RenderTarget::Texture(texture) => {
+                let main_texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+                Self::render_modules_to_view(&mut self.render_modules, &self.gpu, &main_texture_view)
+            }
+        };
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(UIError::RenderModules(errors))
+        }
+    }
+
+    /// Renders every module, in as few render passes as they allow.
+    /// The first pass clears the target, and every layer, and every module that asks for a [`RenderPassMode::Separate`] pass,
+    /// starts a new one, which clears the depth buffer.
+    /// Returns the errors of the modules that failed.
+    fn render_modules_to_view(
+        render_modules: &mut [RegisteredRenderModule],
+        gpu: &RenderingEngineGPU,
+        main_texture_view: &wgpu::TextureView,
+    ) -> Vec<(RenderModuleHandle, UIError)> {
+        let depth_texture_view = gpu.depth_buffer.create_view(&wgpu::TextureViewDescriptor::default());
 
-        let mut cmd_encoder = self.gpu.device
+        let mut errors = Vec::new();
+        // Modules that couldn't prepare aren't rendered, as their GPU resources might be inconsistent.
+        let mut failed = Vec::new();
+        for registered in render_modules.iter_mut() {
+            if let Err(e) = registered.module.prepare_to_render(gpu) {
+                failed.push(registered.handle);
+                errors.push((registered.handle, e));
+            }
+        }
+
+        let mut cmd_encoder = gpu.device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("Render Encoder"),
             });
 
-        let mut render_pass = cmd_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("Render Pass"),
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &main_texture_view,
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color {
-                        r: 1.0,
-                        g: 1.0,
-                        b: 1.0,
-                        a: 1.0,
+        // Splits the modules into the groups that share a pass.
+        let mut passes: Vec<&[RegisteredRenderModule]> = Vec::new();
+        let mut pass_start = 0;
+        for (index, registered) in render_modules.iter().enumerate() {
+            let new_layer = index > 0 && render_modules[index - 1].layer != registered.layer;
+            if index > pass_start && (new_layer || registered.module.render_pass_mode() == RenderPassMode::Separate) {
+                passes.push(&render_modules[pass_start..index]);
+                pass_start = index;
+            }
+        }
+        passes.push(&render_modules[pass_start..]);
+
+        for (pass_index, modules) in passes.into_iter().enumerate() {
+            let color_load = if pass_index == 0 {
+                wgpu::LoadOp::Clear(wgpu::Color {
+                    r: 1.0,
+                    g: 1.0,
+                    b: 1.0,
+                    a: 1.0,
+                })
+            } else {
+                wgpu::LoadOp::Load
+            };
+
+            let mut render_pass = cmd_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: main_texture_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: color_load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &depth_texture_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store
                     }),
-                    store: wgpu::StoreOp::Store,
-                },
-            })],
-            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                view: &depth_texture_view,
-                depth_ops: Some(wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(1.0),
-                    store: wgpu::StoreOp::Store
+                    stencil_ops: None
                 }),
-                stencil_ops: None
-            }),
-            occlusion_query_set: None,
-            timestamp_writes: None,
-        });
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+
+            for registered in modules {
+                if failed.contains(&registered.handle) {
+                    continue;
+                }
+                if let Err(e) = registered.module.commit_render(&mut render_pass) {
+                    errors.push((registered.handle, e));
+                }
+            }
+        }
 
-        for module in self.render_modules.iter_mut() {
-            module.prepare_to_render(&self.gpu);
-            module.commit_render(&mut render_pass);
-            break;
+        gpu.queue.submit(std::iter::once(cmd_encoder.finish()));
+
+        for registered in render_modules.iter_mut() {
+            registered.module.finish_render();
+            errors.extend(registered.module.take_errors().into_iter().map(|e| (registered.handle, e)));
         }
+        errors
+    }
 
-        drop(render_pass);
-        self.gpu.queue.submit(std::iter::once(cmd_encoder.finish()));
+    /// Reads the last rendered frame back from an offscreen target as tightly packed RGBA8 bytes,
+    /// row by row, starting at the top left corner.
+    pub async fn read_frame_rgba(&self) -> Result<Vec<u8>, UIError> {
+        let texture = match &self.gpu.target {
+            RenderTarget::Texture(texture) => texture,
+            RenderTarget::Window { .. } =>
+                return Err(UIError::Readback("Only offscreen render targets can be read back.".to_owned())),
+        };
 
-        // Present the final result to the screen.
-        // TODO: Maybe in case of partial rendering it won't present to the screen.
-        render_target.present();
-        Ok(())
+        read_texture_rgba(&self.gpu.device, &self.gpu.queue, texture).await
     }
 
-    pub fn request_redraw(&mut self, control_flow: &mut ControlFlow) {
+    /// Renders a frame, recovering from the errors that can be recovered from.
+    /// Lost devices are replaced, lost or outdated surfaces are configured again, and frames that time out are skipped.
+    /// While a new device is pending, the frame is skipped, and the window asked to redraw again.
+    pub fn request_redraw(&mut self) -> Result<(), UIError> {
+        if self.is_device_lost() {
+            match self.poll_device_recovery() {
+                Poll::Pending => {
+                    self.request_window_redraw();
+                    return Ok(());
+                }
+                Poll::Ready(recovered) => recovered?,
+            }
+        }
+
         match self.render() {
-            Ok(_) => {}
-            Err(wgpu::SurfaceError::Lost) => self.reconfigure_surface(),
-            // Perhaps this can be better handled?
-            Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
-            Err(e) => eprintln!("{:?}", e),
+            Err(UIError::SurfaceLost) | Err(UIError::Surface(wgpu::SurfaceError::Outdated)) => {
+                self.reconfigure_surface();
+                Ok(())
+            }
+            Err(UIError::Surface(wgpu::SurfaceError::Timeout)) => Ok(()),
+            result => result,
         }
     }
 }
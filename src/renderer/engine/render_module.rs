@@ -1,13 +1,56 @@
-use std::error::Error;
 use wgpu::RenderPass;
 
+use crate::{error::UIError, ui::locale::Locale};
+
 use super::render_engine::RenderingEngineGPU;
 
 /// Trait for a module that can render to an existing render pass.
 /// Things rendered to the screen will possibly interact with other previously
 /// rendered things.
 pub trait RenderModule {
-    fn prepare_to_render(&mut self, engine: &RenderingEngineGPU);
+    /// Uploads whatever changed since the last frame. If this fails, the module isn't rendered in this frame.
+    fn prepare_to_render(&mut self, engine: &RenderingEngineGPU) -> Result<(), UIError>;
+
+    fn commit_render<'pass>(&'pass self, render_pass: &mut RenderPass<'pass>) -> Result<(), UIError>;
+
+    /// Called once per frame after the frame's commands were submitted, even if the module wasn't rendered.
+    /// Modules can free here what the frame didn't use, like cached glyphs.
+    fn finish_render(&mut self) {}
+
+    /// Takes the errors the module ran into outside of preparing and rendering, like the ones of whatever sends it
+    /// what to render. The engine reports them along with the frame's errors, without skipping the module.
+    fn take_errors(&mut self) -> Vec<UIError> {
+        Vec::new()
+    }
+
+    /// Called after the engine switched to a new device, like when the old one was lost.
+    /// Every buffer, texture and pipeline created with the old device must be created again with `engine`'s,
+    /// while everything else (like the primitives or texts to render) should be kept.
+    ///
+    /// By default, the module can't recover, which is reported as an error. Modules that keep no GPU resources
+    /// between frames should return `Ok(())`.
+    fn recreate_gpu_resources(&mut self, _engine: &RenderingEngineGPU) -> Result<(), UIError> {
+        Err(UIError::render_module("This render module can't recreate its GPU resources for a new device."))
+    }
+
+    /// Called when the module is added to an engine, and whenever the engine's locale changes,
+    /// for modules whose output depends on the language or writing direction (like text).
+    fn set_locale(&mut self, _locale: &Locale) {}
+
+    /// Whether this module is rendered in the same render pass as the modules before it.
+    fn render_pass_mode(&self) -> RenderPassMode {
+        RenderPassMode::Shared
+    }
+}
 
-    fn commit_render<'pass>(&'pass self, render_pass: &mut RenderPass<'pass>) -> Result<(), Box<dyn Error>>;
+/// How a module is put into render passes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenderPassMode {
+    /// Renders in the same pass as the modules before it in its layer, sharing its depth buffer.
+    /// The first module of a layer always starts a new pass.
+    #[default]
+    Shared,
+    /// Starts a new render pass, which keeps what was rendered before it but clears the depth buffer,
+    /// so this module (and the ones sharing its pass) is drawn on top of everything before it.
+    Separate,
 }
\ No newline at end of file
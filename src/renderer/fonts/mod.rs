@@ -0,0 +1,124 @@
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+};
+
+use glyphon::{fontdb, FontSystem};
+
+use crate::error::UIError;
+
+const BUNDLED_MONOSPACE_FONT: &[u8; 273900] = include_bytes!("../../../assets/fonts/JetBrainsMono-Regular.ttf");
+const BUNDLED_SANS_SERIF_FONT: &[u8; 15920] = include_bytes!("../../../assets/fonts/Nayten Sans.ttf");
+
+/// The fonts available for rendering text, shared by every text module of an app.
+///
+/// Registries are cheap to clone, and every clone refers to the same fonts.
+/// Text that was already shaped is reshaped when fonts are loaded or the default families change.
+#[derive(Clone)]
+pub struct FontRegistry {
+    font_system: Arc<Mutex<FontSystem>>,
+    /// Incremented on every change, so text modules know when to reshape their text.
+    generation: Arc<AtomicU64>,
+}
+
+impl Default for FontRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FontRegistry {
+    /// Creates a registry with the system's fonts, plus the fonts bundled with the crate,
+    /// which are the default monospace and sans-serif families.
+    ///
+    /// Looking up the system's fonts can take a while, so this should be done once per app.
+    pub fn new() -> Self {
+        Self::from_font_system(FontSystem::new())
+    }
+
+    /// Creates a registry with only the fonts bundled with the crate.
+    /// Text renders the same on every machine, which is useful for snapshots.
+    pub fn without_system_fonts() -> Self {
+        Self::from_font_system(FontSystem::new_with_locale_and_db("en-US".to_owned(), fontdb::Database::new()))
+    }
+
+    fn from_font_system(mut font_system: FontSystem) -> Self {
+        let db = font_system.db_mut();
+        db.load_font_data(BUNDLED_MONOSPACE_FONT.to_vec());
+        db.load_font_data(BUNDLED_SANS_SERIF_FONT.to_vec());
+        db.set_monospace_family("JetBrains Mono");
+        db.set_sans_serif_family("Nayten Sans");
+
+        Self {
+            font_system: Arc::new(Mutex::new(font_system)),
+            generation: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Loads a font (or a font collection) from its data.
+    /// Fails if the data doesn't contain any font that can be used.
+    pub fn load_font_data(&self, bytes: Vec<u8>) -> Result<(), UIError> {
+        let mut font_system = self.lock();
+        let face_count = font_system.db().len();
+        font_system.db_mut().load_font_data(bytes);
+        if font_system.db().len() == face_count {
+            return Err(UIError::InvalidFont);
+        }
+        drop(font_system);
+
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        Ok(())
+    }
+
+    /// Loads a font (or a font collection) from a file.
+    pub fn load_font_file<P: AsRef<Path>>(&self, path: P) -> Result<(), UIError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|source| UIError::FontFile { path: path.to_owned(), source })?;
+        self.load_font_data(bytes)
+    }
+
+    /// Sets the family used for [`crate::renderer::modules::text::FontFamily::SansSerif`].
+    pub fn set_sans_serif_family<S: Into<String>>(&self, family: S) {
+        self.modify(|font_system| font_system.db_mut().set_sans_serif_family(family));
+    }
+
+    /// Sets the family used for [`crate::renderer::modules::text::FontFamily::Serif`].
+    pub fn set_serif_family<S: Into<String>>(&self, family: S) {
+        self.modify(|font_system| font_system.db_mut().set_serif_family(family));
+    }
+
+    /// Sets the family used for [`crate::renderer::modules::text::FontFamily::Monospace`].
+    pub fn set_monospace_family<S: Into<String>>(&self, family: S) {
+        self.modify(|font_system| font_system.db_mut().set_monospace_family(family));
+    }
+
+    /// Names of every font family in the registry, sorted and without duplicates.
+    pub fn families(&self) -> Vec<String> {
+        let font_system = self.lock();
+        let mut families: Vec<String> = font_system.db()
+            .faces()
+            .flat_map(|face| face.families.iter().map(|(name, _)| name.clone()))
+            .collect();
+        families.sort();
+        families.dedup();
+        families
+    }
+
+    /// A number that changes whenever the fonts in this registry change.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Locks the font system, for shaping text.
+    pub fn lock(&self) -> MutexGuard<'_, FontSystem> {
+        self.font_system.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn modify<F: FnOnce(&mut FontSystem)>(&self, f: F) {
+        f(&mut self.lock());
+        self.generation.fetch_add(1, Ordering::AcqRel);
+    }
+}
@@ -10,10 +10,21 @@ pub struct Vertex {
 }
 
 #[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct InstanceData {
     pub transform: [[f32; 4]; 4],
     pub color: [f32; 4],
+    /// Radii of the corners in pixels: top left, top right, bottom right, bottom left.
+    pub corner_radii: [f32; 4],
+    pub border_color: [f32; 4],
+    /// Width of the border in pixels. It is drawn inside the primitive.
+    pub border_width: f32,
+    /// Offset of the drop shadow in pixels.
+    pub shadow_offset: [f32; 2],
+    /// Blur radius of the drop shadow in pixels.
+    pub shadow_blur: f32,
+    /// Color of the drop shadow. It's only drawn if it isn't fully transparent.
+    pub shadow_color: [f32; 4],
 }
 
 const VERTEX_SIZE: wgpu::BufferAddress = std::mem::size_of::<Vertex>() as wgpu::BufferAddress;
@@ -34,6 +45,16 @@ impl Vertex {
 }
 
 impl InstanceData {
+    /// Transform of a rectangle of `size` pixels, with its top left corner at `position` (and `position[2]` as its depth).
+    pub fn rect_transform(position: [f32; 3], size: [f32; 2]) -> [[f32; 4]; 4] {
+        [
+            [size[0], 0.0, 0.0, 0.0],
+            [0.0, size[1], 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [position[0], position[1], position[2], 1.0],
+        ]
+    }
+
     pub fn descriptor() -> wgpu::VertexBufferLayout<'static> {
         wgpu::VertexBufferLayout {
             array_stride: std::mem::size_of::<InstanceData>() as wgpu::BufferAddress,
@@ -64,6 +85,27 @@ impl InstanceData {
                     shader_location: 9,
                     format: wgpu::VertexFormat::Float32x4,
                 },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 20]>() as wgpu::BufferAddress,
+                    shader_location: 10,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 24]>() as wgpu::BufferAddress,
+                    shader_location: 11,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                // Border width, shadow offset and shadow blur, packed together.
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 28]>() as wgpu::BufferAddress,
+                    shader_location: 12,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 32]>() as wgpu::BufferAddress,
+                    shader_location: 13,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
             ],
         }
     }
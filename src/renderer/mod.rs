@@ -1,4 +1,7 @@
 pub mod device;
 pub mod formats;
 pub mod engine;
+pub mod fonts;
 pub mod modules;
+#[cfg(feature = "snapshot")]
+pub mod snapshot;
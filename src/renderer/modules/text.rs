@@ -1,125 +1,450 @@
+use std::{collections::BTreeMap, sync::mpsc::Receiver};
+
 use glyphon::{
-    FontSystem, Resolution, SwashCache, TextArea, TextAtlas, TextBounds,
+    cosmic_text::Align, FontSystem, Resolution, SwashCache, TextArea, TextAtlas, TextBounds,
     TextRenderer as GTextRenderer, Weight,
 };
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    app::UIApp,
+    error::UIError,
+    renderer::{
+        device::with_validation,
+        engine::{render_engine::RenderingEngineGPU, render_module::RenderModule},
+        fonts::FontRegistry,
+    },
+    ui::{geometry::WritingDirection, locale::Locale},
+};
 
-use crate::renderer::engine::{render_engine::RenderingEngineGPU, render_module::RenderModule};
+/// Identifies a text item in a [`TextRenderModule`]. Chosen by whoever submits the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TextId(pub u64);
 
-const TEST_FONT: &[u8; 273900] = include_bytes!("../../../assets/fonts/JetBrainsMono-Regular.ttf");
-const TEST_FONT2: &[u8; 15920] = include_bytes!("../../../assets/fonts/Nayten Sans.ttf");
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FontFamily {
+    SansSerif,
+    Serif,
+    Monospace,
+    Named(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextWrap {
+    /// Lines only break on line breaks.
+    None,
+    /// Lines break at any glyph that doesn't fit.
+    Glyph,
+    /// Lines break between words that don't fit.
+    Word,
+}
+
+/// A piece of text to be rendered.
+///
+/// How it's written depends on the writing direction of its module (see [`TextRenderModule::set_writing_direction`]):
+/// right-to-left text starts at the right edge of its bounds, and vertical text is written in columns
+/// of upright glyphs, which start at the right edge for right-to-left columns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextItem {
+    pub content: String,
+    /// Top left corner of the text, in pixels.
+    pub position: (f32, f32),
+    /// Size of the box the text wraps in. Glyphs outside of it are clipped.
+    /// Right-to-left and vertical text need finite bounds, to know where its lines or columns start.
+    /// Without a width, right-to-left lines and columns start at the left edge instead.
+    pub bounds: (f32, f32),
+    pub family: FontFamily,
+    /// Font size, in pixels.
+    pub size: f32,
+    /// Distance between lines, in pixels. For vertical text, that's the distance between columns,
+    /// and between the glyphs in a column.
+    pub line_height: f32,
+    /// Color as RGBA.
+    pub color: [u8; 4],
+    pub wrap: TextWrap,
+}
+
+impl Default for TextItem {
+    fn default() -> Self {
+        Self {
+            content: String::new(),
+            position: (0.0, 0.0),
+            bounds: (f32::MAX, f32::MAX),
+            family: FontFamily::SansSerif,
+            size: 12.0,
+            line_height: 16.0,
+            color: [0x00, 0x00, 0x00, 0xFF],
+            wrap: TextWrap::Word,
+        }
+    }
+}
+
+/// A change to the texts of a [`TextRenderModule`], for sending them from elsewhere.
+#[derive(Debug, Clone)]
+pub enum TextChange {
+    Set(TextId, TextItem),
+    Remove(TextId),
+}
+
+/// A text item along with its shaped buffers: a single one for horizontal text, and one per column for vertical text.
+struct CachedText {
+    item: TextItem,
+    buffers: Vec<PositionedBuffer>,
+}
+
+/// A shaped buffer, and where it goes relative to the position of its text item.
+struct PositionedBuffer {
+    offset: (f32, f32),
+    buffer: glyphon::Buffer,
+}
 
 pub struct TextRenderModule {
     gtext_renderer: GTextRenderer,
     cache: SwashCache,
     atlas: TextAtlas,
-    font_system: FontSystem,
+    fonts: FontRegistry,
+    /// Generation of `fonts` the texts were last shaped with.
+    font_generation: u64,
+    texts: BTreeMap<TextId, CachedText>,
+    change_receiver: Option<Receiver<TextChange>>,
+    direction: WritingDirection,
 }
 
 impl TextRenderModule {
-    pub fn new(
-        gpu: &RenderingEngineGPU
-    ) -> Self {
-        let mut font_system = FontSystem::new();
-
-        font_system.db_mut().load_font_data(TEST_FONT.into());
-        font_system.db_mut().load_font_data(TEST_FONT2.into());
-
-        font_system.db_mut().set_monospace_family("JetBrains Mono");
-        font_system.db_mut().set_sans_serif_family("Nayten Sans");
+    /// Creates a text module for the main window of an app, which renders with the app's fonts.
+    pub fn new<T>(app: &UIApp<T>) -> Result<Self, UIError> {
+        Self::from_gpu(&app.get_render_engine().gpu, app.fonts())
+    }
 
+    /// Creates a text module straight from the GPU handles of a rendering engine, which renders with the fonts in `fonts`,
+    /// like the ones from [`crate::app::UIApp::fonts`]. Useful for engines without an app (like headless ones).
+    pub fn from_gpu(
+        gpu: &RenderingEngineGPU,
+        fonts: &FontRegistry,
+    ) -> Result<Self, UIError> {
         let cache = SwashCache::new();
-        let mut atlas: TextAtlas = TextAtlas::new(&gpu.device, &gpu.queue, gpu.surface_config.format);
-
-        let text_renderer =
-            GTextRenderer::new(
+        let (atlas, text_renderer) = with_validation(&gpu.device, || {
+            let mut atlas = TextAtlas::new(&gpu.device, &gpu.queue, gpu.surface_config.format);
+            let text_renderer = GTextRenderer::new(
                 &mut atlas,
                 &gpu.device,
                 wgpu::MultisampleState::default(),
-                None
+                // Text shares the render pass with other modules, so it must be compatible with its depth buffer.
+                Some(wgpu::DepthStencilState {
+                    format: wgpu::TextureFormat::Depth32Float,
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::Always,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
             );
+            (atlas, text_renderer)
+        })?;
 
-        Self {
+        Ok(Self {
             gtext_renderer: text_renderer,
             atlas,
             cache,
-            font_system,
+            font_generation: fonts.generation(),
+            fonts: fonts.clone(),
+            texts: BTreeMap::new(),
+            change_receiver: None,
+            direction: WritingDirection::default(),
+        })
+    }
+
+    /// Changes the direction every text is written in, reshaping them if it's different.
+    /// Modules added to an engine follow the direction of its locale, see [`crate::app::UIApp::set_locale`].
+    pub fn set_writing_direction(&mut self, direction: WritingDirection) {
+        if direction == self.direction {
+            return;
+        }
+        self.direction = direction;
+
+        let mut font_system = self.fonts.lock();
+        for cached in self.texts.values_mut() {
+            cached.buffers = shape_text(&mut font_system, &cached.item, direction);
+        }
+    }
+
+    pub fn writing_direction(&self) -> WritingDirection {
+        self.direction
+    }
+
+    /// Adds a text item, or updates the one with the same id.
+    /// The text is only reshaped if its content, font or wrapping changed, and only laid out again if its bounds changed.
+    pub fn set_text(&mut self, id: TextId, item: TextItem) {
+        let mut font_system = self.fonts.lock();
+
+        match self.texts.get_mut(&id) {
+            Some(cached) => {
+                let needs_reshape = cached.item.content != item.content
+                    || cached.item.family != item.family
+                    || cached.item.size != item.size
+                    || cached.item.line_height != item.line_height
+                    || cached.item.wrap != item.wrap;
+                let needs_relayout = cached.item.bounds != item.bounds;
+
+                // Vertical text is split in columns that depend on the bounds, so it has to be shaped again.
+                if needs_reshape || (needs_relayout && self.direction.is_vertical()) {
+                    cached.buffers = shape_text(&mut font_system, &item, self.direction);
+                } else if needs_relayout {
+                    for positioned in cached.buffers.iter_mut() {
+                        positioned.buffer.set_size(&mut font_system, item.bounds.0, item.bounds.1);
+                        positioned.buffer.shape_until_scroll(&mut font_system);
+                    }
+                }
+                cached.item = item;
+            }
+            None => {
+                let buffers = shape_text(&mut font_system, &item, self.direction);
+                self.texts.insert(id, CachedText { item, buffers });
+            }
         }
     }
 
+    pub fn remove_text(&mut self, id: TextId) -> Option<TextItem> {
+        self.texts.remove(&id).map(|cached| cached.item)
+    }
+
+    pub fn text(&self, id: TextId) -> Option<&TextItem> {
+        self.texts.get(&id).map(|cached| &cached.item)
+    }
+
+    pub fn clear_texts(&mut self) {
+        self.texts.clear();
+    }
+
+    /// Makes this module apply the changes sent through `receiver` before every render.
+    pub fn listen_to_text_changes(&mut self, receiver: Receiver<TextChange>) {
+        self.change_receiver = Some(receiver);
+    }
+
     pub fn prepare_text_areas(
         &mut self,
-        queue: &wgpu::Queue,
-        device: &wgpu::Device,
-        config: &wgpu::SurfaceConfiguration,
-        window_width: u32, window_height: u32
+        gpu: &RenderingEngineGPU,
     ) -> Result<(), glyphon::PrepareError> {
-        let buffer = &self.get_test_text_buffer("Hi", (0.0, 0.0, window_width as f32, window_height as f32));
-
-        let area = TextArea {
-            buffer,
-            left: 0.0,
-            top: 0.0,
-            scale: 1.0,
-            bounds: TextBounds {
-                left: 0,
-                top: 0,
-                right: window_width as i32,
-                bottom: window_height as i32,
-            },
-            default_color: glyphon::Color::rgb(0xFF, 0xFF, 0xFF),
-        };
+        let mut font_system = self.fonts.lock();
+
+        // Fonts changed since the texts were shaped, so they might look different now.
+        let font_generation = self.fonts.generation();
+        if font_generation != self.font_generation {
+            for cached in self.texts.values_mut() {
+                cached.buffers = shape_text(&mut font_system, &cached.item, self.direction);
+            }
+            self.font_generation = font_generation;
+        }
 
-        return self.gtext_renderer.prepare(
-            device,
-            queue,
-            &mut self.font_system,
+        let areas = self.texts.values().flat_map(|cached| {
+            let (left, top) = cached.item.position;
+            let (width, height) = cached.item.bounds;
+            let [r, g, b, a] = cached.item.color;
+
+            cached.buffers.iter().map(move |positioned| TextArea {
+                buffer: &positioned.buffer,
+                left: left + positioned.offset.0,
+                top: top + positioned.offset.1,
+                scale: 1.0,
+                // Every column of vertical text is clipped to the bounds of the whole item.
+                bounds: TextBounds {
+                    left: left as i32,
+                    top: top as i32,
+                    right: (left + width) as i32,
+                    bottom: (top + height) as i32,
+                },
+                default_color: glyphon::Color::rgba(r, g, b, a),
+            })
+        });
+
+        self.gtext_renderer.prepare(
+            &gpu.device,
+            &gpu.queue,
+            &mut font_system,
             &mut self.atlas,
             Resolution {
-                width: config.width,
-                height: config.height,
+                width: gpu.surface_config.width,
+                height: gpu.surface_config.height,
             },
-            vec![area],
+            areas,
             &mut self.cache,
-        );
+        )
     }
+}
 
-    pub fn get_test_text_buffer(
-        &mut self,
-        text: &str,
-        aabb: (f32, f32, f32, f32),
-    ) -> glyphon::Buffer {
-        let mut bufferw =
-            glyphon::Buffer::new(&mut self.font_system, glyphon::Metrics::new(12.0, 12.0));
+/// Creates and shapes the buffers for a text item, written in `direction`.
+fn shape_text(font_system: &mut FontSystem, item: &TextItem, direction: WritingDirection) -> Vec<PositionedBuffer> {
+    let shaping = if direction.is_right_to_left() || needs_advanced_shaping(&item.content) {
+        glyphon::Shaping::Advanced
+    } else {
+        glyphon::Shaping::Basic
+    };
+
+    // Right-to-left lines and columns start at the right edge, which text without a width doesn't have.
+    let has_width = item.bounds.0.is_finite() && item.bounds.0 < f32::MAX;
 
-        let attrs_normal = glyphon::Attrs::new()
-            .family(glyphon::Family::Monospace)
-            .color(glyphon::Color::rgb(0xDD, 0xDD, 0xDD));
+    if !direction.is_vertical() {
+        let mut buffer = create_text_buffer(font_system, item, &item.content, item.bounds, shaping);
+        if direction.is_right_to_left() {
+            // Lines of right-to-left text are already aligned to the right, but this aligns the left-to-right
+            // ones too, so every line starts at the same edge: the right one, or the left one without a width.
+            let align = if has_width { Align::Right } else { Align::Left };
+            align_lines(font_system, &mut buffer, align);
+        }
+        return vec![PositionedBuffer { offset: (0.0, 0.0), buffer }];
+    }
+
+    let glyphs_per_column = ((item.bounds.1 / item.line_height).floor() as usize).max(1);
+    let right_to_left = direction.is_right_to_left() && has_width;
+
+    vertical_columns(&item.content, glyphs_per_column)
+        .into_iter()
+        .enumerate()
+        .map(|(index, column)| {
+            let mut buffer = create_text_buffer(
+                font_system, item, &column, (item.line_height, item.bounds.1), shaping);
+            align_lines(font_system, &mut buffer, Align::Center);
+
+            let x = index as f32 * item.line_height;
+            let x = if right_to_left { item.bounds.0 - item.line_height - x } else { x };
+            PositionedBuffer { offset: (x, 0.0), buffer }
+        })
+        .collect()
+}
 
-        bufferw.set_size(&mut self.font_system, aabb.2, aabb.3);
-        bufferw.set_rich_text(
-            &mut self.font_system,
-            [(
-                "This is a contrived example of UI Composer's capabilities regarding text rendering.\n\nSo, it uses Glyphon under the hood for text rendering (which uses cosmic-text for shaping, etagere for atlas-ing and wgpu middleware for rendering).", attrs_normal
-            )],
-            glyphon::Shaping::Basic
-        );
-        bufferw.set_wrap(&mut self.font_system, glyphon::Wrap::Word);
-        bufferw.shape_until_scroll(&mut self.font_system);
+/// Splits text into the columns it's written in vertically: one grapheme cluster per line
+/// (so accents stay on their letters), `glyphs_per_column` lines per column, and a new column for every paragraph.
+fn vertical_columns(content: &str, glyphs_per_column: usize) -> Vec<String> {
+    content
+        .split('\n')
+        .flat_map(|paragraph| {
+            let glyphs: Vec<&str> = paragraph.graphemes(true).collect();
+            if glyphs.is_empty() {
+                return vec![String::new()];
+            }
+            glyphs
+                .chunks(glyphs_per_column)
+                .map(|column| column.join("\n"))
+                .collect()
+        })
+        .collect()
+}
 
-        return bufferw;
+/// Whether text contains scripts whose glyphs change with their neighbours (like Arabic and Hebrew),
+/// which basic shaping gets wrong.
+fn needs_advanced_shaping(content: &str) -> bool {
+    content.chars().any(|c| matches!(c as u32,
+        // Combining diacritical marks.
+        0x0300..=0x036F
+        // Hebrew, Arabic, Syriac, Arabic Supplement, Thaana, NKo, Samaritan, Mandaic and Arabic Extended.
+        | 0x0590..=0x08FF
+        // Indic scripts, Thai, Lao, Tibetan and Myanmar.
+        | 0x0900..=0x109F
+        // Hebrew and Arabic presentation forms.
+        | 0xFB1D..=0xFDFF
+        | 0xFE70..=0xFEFF
+    ))
+}
+
+/// Aligns every line of a shaped buffer, and lays it out again.
+fn align_lines(font_system: &mut FontSystem, buffer: &mut glyphon::Buffer, align: Align) {
+    for line in buffer.lines.iter_mut() {
+        line.set_align(Some(align));
     }
+    buffer.shape_until_scroll(font_system);
+}
+
+/// Creates and shapes a buffer for (part of) the content of a text item.
+fn create_text_buffer(
+    font_system: &mut FontSystem,
+    item: &TextItem,
+    content: &str,
+    size: (f32, f32),
+    shaping: glyphon::Shaping,
+) -> glyphon::Buffer {
+    let mut buffer =
+        glyphon::Buffer::new(font_system, glyphon::Metrics::new(item.size, item.line_height));
+
+    let family = match &item.family {
+        FontFamily::SansSerif => glyphon::Family::SansSerif,
+        FontFamily::Serif => glyphon::Family::Serif,
+        FontFamily::Monospace => glyphon::Family::Monospace,
+        FontFamily::Named(name) => glyphon::Family::Name(name),
+    };
+    let wrap = match item.wrap {
+        TextWrap::None => glyphon::Wrap::None,
+        TextWrap::Glyph => glyphon::Wrap::Glyph,
+        TextWrap::Word => glyphon::Wrap::Word,
+    };
+
+    buffer.set_size(font_system, size.0, size.1);
+    buffer.set_wrap(font_system, wrap);
+    buffer.set_text(
+        font_system,
+        content,
+        glyphon::Attrs::new().family(family),
+        shaping
+    );
+    buffer.shape_until_scroll(font_system);
+
+    buffer
 }
 
 impl RenderModule for TextRenderModule {
-    fn prepare_to_render(&mut self, engine: &RenderingEngineGPU) {
-        self.prepare_text_areas(&engine.queue, &engine.device, &engine.surface_config, engine.window_size.width, engine.window_size.height);
+    fn prepare_to_render(&mut self, engine: &RenderingEngineGPU) -> Result<(), UIError> {
+        if let Some(receiver) = self.change_receiver.take() {
+            for change in receiver.try_iter() {
+                match change {
+                    TextChange::Set(id, item) => self.set_text(id, item),
+                    TextChange::Remove(id) => { self.remove_text(id); }
+                }
+            }
+            self.change_receiver = Some(receiver);
+        }
+
+        self.prepare_text_areas(engine).map_err(UIError::render_module)
+    }
+
+    fn set_locale(&mut self, locale: &Locale) {
+        self.set_writing_direction(locale.direction);
+    }
+
+    fn recreate_gpu_resources(&mut self, engine: &RenderingEngineGPU) -> Result<(), UIError> {
+        // Shaped text lives on the CPU, only the atlas and the pipeline need to be created again.
+        let fresh = Self::from_gpu(engine, &self.fonts)?;
+        self.gtext_renderer = fresh.gtext_renderer;
+        self.atlas = fresh.atlas;
+        self.cache = fresh.cache;
+        Ok(())
     }
 
     fn commit_render<'pass>(
         &'pass self,
         render_pass: &mut wgpu::RenderPass<'pass>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        self.gtext_renderer.render(&self.atlas, render_pass)?;
-        Ok(())
+    ) -> Result<(), UIError> {
+        self.gtext_renderer.render(&self.atlas, render_pass).map_err(UIError::render_module)
+    }
+
+    fn finish_render(&mut self) {
+        // Evicts the glyphs this frame didn't use, so the atlas doesn't keep growing as texts change.
+        self.atlas.trim();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vertical_columns_keep_grapheme_clusters_together() {
+        // "é" written as an "e" and a combining acute accent, and a flag made of two regional indicators.
+        let content = "ne\u{301}\u{1F1EB}\u{1F1F7}";
+        assert_eq!(vertical_columns(content, 2), vec!["n\ne\u{301}", "\u{1F1EB}\u{1F1F7}"]);
+        assert_eq!(vertical_columns(content, 10), vec!["n\ne\u{301}\n\u{1F1EB}\u{1F1F7}"]);
+    }
+
+    #[test]
+    fn every_paragraph_starts_a_column() {
+        assert_eq!(vertical_columns("ab\n\ncde", 2), vec!["a\nb", "", "c\nd", "e"]);
+        assert_eq!(vertical_columns("", 2), vec![""]);
     }
 }
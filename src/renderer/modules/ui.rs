@@ -1,69 +1,325 @@
-use crate::{app::UIApp, renderer::{
+use crate::{app::UIApp, error::UIError, renderer::{
+    device::with_validation,
     engine::{self, render_engine::{RenderingEngine, RenderingEngineGPU}, render_module::RenderModule},
     formats::vertex::{InstanceData, Vertex},
 }};
+use std::{ops::Range, sync::mpsc::Receiver};
 use wgpu::{util::DeviceExt, SurfaceConfiguration};
 
+/// A replacement of a range of primitives by new ones, which might be more or less numerous.
+#[derive(Debug, Clone)]
+pub struct PrimitiveSplice {
+    pub range: Range<usize>,
+    pub primitives: Vec<InstanceData>,
+}
+
+/// Receives the splices of whatever renders into a module (like a [`crate::ui::reactive::RerenderDriver`]),
+/// or the errors it ran into, which the module reports after rendering.
+pub type SpliceReceiver = Receiver<Result<PrimitiveSplice, UIError>>;
+
 pub struct PrimitiveRenderModule {
-    render_pipeline: wgpu::RenderPipeline,
+    opaque_pipeline: wgpu::RenderPipeline,
+    translucent_pipeline: wgpu::RenderPipeline,
     primitive_mesh: Mesh2D<'static>,
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
     instance_buffer: wgpu::Buffer,
+    instance_capacity: usize,
+    /// The translucent instances only, sorted back to front for the translucent pass.
+    sorted_instance_buffer: wgpu::Buffer,
+    /// How many instances are in `sorted_instance_buffer`.
+    translucent_count: usize,
     instances: Vec<InstanceData>,
     uniform_bind_group: wgpu::BindGroup,
     uniform_buffer: wgpu::Buffer,
     uniforms: PrimitiveRenderModuleUniforms,
+    splice_receiver: Option<SpliceReceiver>,
+    /// Errors received instead of splices, reported by [`RenderModule::take_errors`].
+    received_errors: Vec<UIError>,
+    dirty_instances: DirtyRanges,
+    /// Whether `sorted_instance_buffer` has to be sorted and uploaded again,
+    /// because a translucent instance was added, removed, or changed.
+    dirty_sorting: bool,
+    shrink_instance_buffer: bool,
+    instance_buffer_reallocations: usize,
+}
+
+/// How many instances the instance buffer can hold when the module is created.
+pub const INITIAL_INSTANCE_CAPACITY: usize = 1024;
+
+/// Statistics about the GPU memory used by a [`PrimitiveRenderModule`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrimitiveRenderModuleStats {
+    pub instance_count: usize,
+    /// How many of the instances are translucent, and drawn in the translucent pass.
+    pub translucent_count: usize,
+    /// How many instances fit in the instance buffer before it needs to grow.
+    pub instance_capacity: usize,
+    pub instance_buffer_size: wgpu::BufferAddress,
+    /// How many times the instance buffer was reallocated, either to grow or to shrink.
+    pub instance_buffer_reallocations: usize,
+}
+
+/// How many instances the instance buffer should hold for `len` instances, starting from `capacity`.
+/// It doubles until everything fits and, if `shrink` is set, halves while under a quarter is used,
+/// never going below [`INITIAL_INSTANCE_CAPACITY`].
+fn fitted_capacity(mut capacity: usize, len: usize, shrink: bool) -> usize {
+    while capacity < len {
+        capacity *= 2;
+    }
+    if shrink {
+        while capacity / 2 >= INITIAL_INSTANCE_CAPACITY && len < capacity / 4 {
+            capacity /= 2;
+        }
+    }
+    capacity
+}
+
+/// Sorted, non-overlapping set of index ranges that changed and need to be uploaded.
+#[derive(Debug, Default)]
+struct DirtyRanges(Vec<Range<usize>>);
+
+impl DirtyRanges {
+    /// Marks a range as dirty, merging it with the ranges it overlaps or touches.
+    fn mark(&mut self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+
+        let first = self.0.partition_point(|r| r.end < range.start);
+        let last = self.0.partition_point(|r| r.start <= range.end);
+
+        let merged = if first < last {
+            self.0[first].start.min(range.start)..self.0[last - 1].end.max(range.end)
+        } else {
+            range
+        };
+        self.0.splice(first..last, std::iter::once(merged));
+    }
+
+    fn take(&mut self) -> Vec<Range<usize>> {
+        std::mem::take(&mut self.0)
+    }
 }
 
 impl PrimitiveRenderModule {
-    pub fn new<T>(app: &UIApp<T>) -> Self {
-        let gpu = &app.get_render_engine().gpu;
+    pub fn new<T>(app: &UIApp<T>) -> Result<Self, UIError> {
+        Self::from_gpu(&app.get_render_engine().gpu)
+    }
+
+    /// Creates the module straight from the GPU handles of a rendering engine,
+    /// which is useful for engines without an app (like headless ones).
+    pub fn from_gpu(gpu: &RenderingEngineGPU) -> Result<Self, UIError> {
         let primitive_mesh = get_quad_mesh();
-        let (vertex_buffer, index_buffer, instance_buffer) =
+        let (vertex_buffer, index_buffer) =
             create_primitive_mesh_buffers(&primitive_mesh, &gpu.device);
+        let instance_capacity = INITIAL_INSTANCE_CAPACITY;
+        let instance_buffer = create_instance_buffer(instance_capacity, &gpu.device);
+        let sorted_instance_buffer = create_instance_buffer(instance_capacity, &gpu.device);
         let instances = Vec::new();
         let uniforms = PrimitiveRenderModuleUniforms::default();
         let uniform_buffer = create_uniform_buffer(&uniforms, &gpu.device);
         let uniform_bind_group_layout = create_uniform_bind_group_layout(&gpu.device);
         let uniform_bind_group =
             create_uniform_bind_group(&uniform_bind_group_layout, &uniform_buffer, &gpu.device);
-        let shader_descriptor = get_main_shader();
-        let shader = gpu.device.create_shader_module(get_main_shader());
-        let render_pipeline =
-            create_main_render_pipeline(&gpu.device, shader, &gpu.surface_config, uniform_bind_group_layout);
-
-        Self {
-            render_pipeline,
+        let (opaque_pipeline, translucent_pipeline) = with_validation(&gpu.device, || {
+            let shader = gpu.device.create_shader_module(get_main_shader());
+            (
+                create_main_render_pipeline(
+                    &gpu.device, &shader, &gpu.surface_config, &uniform_bind_group_layout, PrimitivePass::Opaque),
+                create_main_render_pipeline(
+                    &gpu.device, &shader, &gpu.surface_config, &uniform_bind_group_layout, PrimitivePass::Translucent),
+            )
+        })?;
+
+        Ok(Self {
+            opaque_pipeline,
+            translucent_pipeline,
             primitive_mesh,
             vertex_buffer,
             index_buffer,
             instance_buffer,
+            instance_capacity,
+            sorted_instance_buffer,
+            translucent_count: 0,
             instances,
             uniforms,
             uniform_buffer,
             uniform_bind_group,
+            splice_receiver: None,
+            received_errors: Vec::new(),
+            dirty_instances: DirtyRanges::default(),
+            dirty_sorting: false,
+            shrink_instance_buffer: false,
+            instance_buffer_reallocations: 0,
+        })
+    }
+
+    pub fn stats(&self) -> PrimitiveRenderModuleStats {
+        PrimitiveRenderModuleStats {
+            instance_count: self.instances.len(),
+            translucent_count: self.translucent_count,
+            instance_capacity: self.instance_capacity,
+            instance_buffer_size: self.instance_buffer.size(),
+            instance_buffer_reallocations: self.instance_buffer_reallocations,
         }
     }
 
+    /// Whether the instance buffer should shrink (by half) once less than a quarter of it is in use.
+    /// It never shrinks below [`INITIAL_INSTANCE_CAPACITY`]. Disabled by default.
+    pub fn set_shrink_instance_buffer(&mut self, shrink: bool) {
+        self.shrink_instance_buffer = shrink;
+    }
+
+    /// Makes this module apply the splices sent through `receiver` before every render,
+    /// like the ones emitted by a [`crate::ui::reactive::RerenderDriver`].
+    /// The errors received along with them are reported as this module's.
+    pub fn listen_to_splices(&mut self, receiver: SpliceReceiver) {
+        self.splice_receiver = Some(receiver);
+    }
+
+    /// Replaces the primitives in `splice.range` with `splice.primitives`.
+    /// If the range ends past the current primitives, the gap is filled with empty primitives.
+    /// Fails if the range ends before it starts.
+    pub fn splice_primitives(&mut self, gpu: &RenderingEngineGPU, splice: PrimitiveSplice) -> Result<(), UIError> {
+        self.apply_splice(splice)?;
+        self.upload_dirty_instances(gpu);
+        Ok(())
+    }
+
+    /// Overwrites the primitives starting at `start` with `primitives`, without moving any other primitive.
+    pub fn replace_primitives(&mut self, gpu: &RenderingEngineGPU, start: usize, primitives: &[InstanceData]) {
+        self.apply_valid_splice(PrimitiveSplice {
+            range: start..(start + primitives.len()),
+            primitives: primitives.to_vec(),
+        });
+        self.upload_dirty_instances(gpu);
+    }
+
+    /// Inserts primitives at `index`, moving the ones after it forward.
+    pub fn insert_primitives(&mut self, gpu: &RenderingEngineGPU, index: usize, primitives: &[InstanceData]) {
+        self.apply_valid_splice(PrimitiveSplice {
+            range: index..index,
+            primitives: primitives.to_vec(),
+        });
+        self.upload_dirty_instances(gpu);
+    }
+
+    fn apply_splice(&mut self, splice: PrimitiveSplice) -> Result<(), UIError> {
+        if splice.range.start > splice.range.end {
+            return Err(UIError::InvalidSplice(splice.range));
+        }
+        self.apply_valid_splice(splice);
+        Ok(())
+    }
+
+    /// Applies a splice whose range doesn't end before it starts.
+    fn apply_valid_splice(&mut self, splice: PrimitiveSplice) {
+        if splice.range.end > self.instances.len() {
+            self.instances.resize(splice.range.end, bytemuck::Zeroable::zeroed());
+        }
+
+        // Opaque instances are drawn straight from the instance buffer, so only translucent ones need sorting.
+        let is_translucent = |instance: &InstanceData| PrimitiveOpacity::of(instance) == PrimitiveOpacity::Translucent;
+        if self.instances[splice.range.clone()].iter().any(is_translucent) || splice.primitives.iter().any(is_translucent) {
+            self.dirty_sorting = true;
+        }
+
+        let start = splice.range.start;
+        let length_changed = splice.range.len() != splice.primitives.len();
+        let replaced_end = start + splice.primitives.len();
+        self.instances.splice(splice.range, splice.primitives);
+
+        // If the amount of primitives changed, everything after them has moved.
+        if length_changed {
+            self.dirty_instances.mark(start..self.instances.len());
+        } else {
+            self.dirty_instances.mark(start..replaced_end);
+        }
+    }
+
+    /// Writes only the instances that changed since the last upload to the instance buffer.
+    fn upload_dirty_instances(&mut self, gpu: &RenderingEngineGPU) {
+        const INSTANCE_SIZE: usize = std::mem::size_of::<InstanceData>();
+
+        self.fit_instance_buffer(&gpu.device);
+
+        for range in self.dirty_instances.take() {
+            let range = range.start..range.end.min(self.instances.len());
+            if range.is_empty() {
+                continue;
+            }
+
+            gpu.queue.write_buffer(
+                &self.instance_buffer,
+                (range.start * INSTANCE_SIZE) as wgpu::BufferAddress,
+                bytemuck::cast_slice(&self.instances[range]),
+            );
+        }
+
+        if self.dirty_sorting {
+            let translucent: Vec<InstanceData> = self.instances
+                .iter()
+                .filter(|instance| PrimitiveOpacity::of(instance) == PrimitiveOpacity::Translucent)
+                .copied()
+                .collect();
+            gpu.queue.write_buffer(
+                &self.sorted_instance_buffer,
+                0,
+                bytemuck::cast_slice(&sort_back_to_front(&translucent)),
+            );
+            self.translucent_count = translucent.len();
+            self.dirty_sorting = false;
+        }
+    }
+
+    /// Reallocates the instance buffer if the instances don't fit in it anymore (doubling its capacity),
+    /// or if shrinking is enabled and they take too little of it (halving its capacity).
+    fn fit_instance_buffer(&mut self, device: &wgpu::Device) {
+        let capacity = fitted_capacity(self.instance_capacity, self.instances.len(), self.shrink_instance_buffer);
+        if capacity == self.instance_capacity {
+            return;
+        }
+
+        self.instance_buffer = create_instance_buffer(capacity, device);
+        self.sorted_instance_buffer = create_instance_buffer(capacity, device);
+        self.instance_capacity = capacity;
+        self.instance_buffer_reallocations += 1;
+        // The new buffers are empty, so everything has to be uploaded again.
+        self.dirty_instances.mark(0..self.instances.len());
+        self.dirty_sorting = true;
+    }
+
     pub fn push_raw_primitives(
         &mut self,
         gpu: &RenderingEngineGPU,
         primitive_instances: &Vec<InstanceData>,
     ) {
         self.instances.clear();
-        self.instances.clone_from(&primitive_instances);
-
-        gpu.queue.write_buffer(
-            &self.instance_buffer,
-            0,
-            bytemuck::cast_slice(&self.instances[..]),
-        );
+        self.instances.clone_from(primitive_instances);
+        self.dirty_instances.mark(0..self.instances.len());
+        self.dirty_sorting = true;
+        self.upload_dirty_instances(gpu);
     }
 }
 
 impl RenderModule for PrimitiveRenderModule {
-    fn prepare_to_render(&mut self, engine: &RenderingEngineGPU) {
+    fn prepare_to_render(&mut self, engine: &RenderingEngineGPU) -> Result<(), UIError> {
+        if let Some(receiver) = self.splice_receiver.take() {
+            for received in receiver.try_iter() {
+                match received {
+                    Ok(splice) => {
+                        if let Err(e) = self.apply_splice(splice) {
+                            self.received_errors.push(e);
+                        }
+                    }
+                    Err(e) => self.received_errors.push(e),
+                }
+            }
+            self.splice_receiver = Some(receiver);
+        }
+        self.upload_dirty_instances(engine);
+
         self.uniforms.window_size = calc_px_to_wgpu_matrix(
             engine.window_size.width as f32,
             engine.window_size.height as f32
@@ -74,24 +330,59 @@ impl RenderModule for PrimitiveRenderModule {
             0,
             bytemuck::cast_slice(&[self.uniforms]),
         );
+
+        Ok(())
+    }
+
+    fn take_errors(&mut self) -> Vec<UIError> {
+        std::mem::take(&mut self.received_errors)
+    }
+
+    fn recreate_gpu_resources(&mut self, engine: &RenderingEngineGPU) -> Result<(), UIError> {
+        let fresh = Self::from_gpu(engine)?;
+        self.opaque_pipeline = fresh.opaque_pipeline;
+        self.translucent_pipeline = fresh.translucent_pipeline;
+        self.vertex_buffer = fresh.vertex_buffer;
+        self.index_buffer = fresh.index_buffer;
+        self.instance_buffer = fresh.instance_buffer;
+        self.sorted_instance_buffer = fresh.sorted_instance_buffer;
+        self.instance_capacity = fresh.instance_capacity;
+        self.uniform_bind_group = fresh.uniform_bind_group;
+        self.uniform_buffer = fresh.uniform_buffer;
+
+        // The new buffers are empty, so every instance has to be uploaded again.
+        self.dirty_instances.mark(0..self.instances.len());
+        self.dirty_sorting = true;
+        Ok(())
     }
 
     fn commit_render<'pass>(
         &'pass self,
         render_pass: &mut wgpu::RenderPass<'pass>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        render_pass.set_pipeline(&self.render_pipeline);
+    ) -> Result<(), UIError> {
         render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
         render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
         render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
 
+        // The opaque pipeline skips the instances that aren't opaque, so each instance is drawn once.
+        render_pass.set_pipeline(&self.opaque_pipeline);
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
         render_pass.draw_indexed(
             0..(self.primitive_mesh.1.len() as _),
             0,
             0..(self.instances.len() as _),
         );
 
+        if self.translucent_count > 0 {
+            render_pass.set_pipeline(&self.translucent_pipeline);
+            render_pass.set_vertex_buffer(1, self.sorted_instance_buffer.slice(..));
+            render_pass.draw_indexed(
+                0..(self.primitive_mesh.1.len() as _),
+                0,
+                0..(self.translucent_count as _),
+            );
+        }
+
         Ok(())
     }
 }
@@ -124,6 +415,11 @@ pub fn get_quad_mesh() -> Mesh2D<'static> {
 }
 
 pub fn to_linear_rgb(c: u32) -> [f32; 4] {
+    to_linear_rgba((c << 8) | 0xFF)
+}
+
+/// Converts an sRGB color with alpha, like `0xRRGGBBAA`, to linear RGB. Alpha is kept as is.
+pub fn to_linear_rgba(c: u32) -> [f32; 4] {
     let f = |xu: u32| {
         let x = (xu & 0xFF) as f32 / 255.0;
         if x > 0.04045 {
@@ -132,17 +428,52 @@ pub fn to_linear_rgb(c: u32) -> [f32; 4] {
             x / 12.92
         }
     };
-    [f(c >> 16), f(c >> 8), f(c), 1.0]
+    [f(c >> 24), f(c >> 16), f(c >> 8), (c & 0xFF) as f32 / 255.0]
+}
+
+/// Which pass a primitive is drawn in. The shader classifies primitives the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrimitiveOpacity {
+    /// Its fill (and border, if it has one) are opaque, and it has no shadow.
+    Opaque,
+    /// Some of it is blended with what's behind it.
+    Translucent,
+    /// Nothing of it is visible, so it isn't drawn at all.
+    Invisible,
+}
+
+impl PrimitiveOpacity {
+    pub fn of(instance: &InstanceData) -> Self {
+        let has_border = instance.border_width > 0.0;
+        let has_shadow = instance.shadow_color[3] > 0.0;
+
+        if instance.color[3] >= 1.0 && (!has_border || instance.border_color[3] >= 1.0) && !has_shadow {
+            PrimitiveOpacity::Opaque
+        } else if instance.color[3] <= 0.0 && (!has_border || instance.border_color[3] <= 0.0) && !has_shadow {
+            PrimitiveOpacity::Invisible
+        } else {
+            PrimitiveOpacity::Translucent
+        }
+    }
+}
+
+/// Instances ordered from the furthest to the closest to the camera, which is the order translucent primitives are blended in.
+/// Instances at the same depth keep their order.
+pub fn sort_back_to_front(instances: &[InstanceData]) -> Vec<InstanceData> {
+    let mut sorted = instances.to_vec();
+    // The depth of an instance is the z of its translation.
+    sorted.sort_by(|a, b| b.transform[3][2].total_cmp(&a.transform[3][2]));
+    sorted
 }
 
 /** Converts from px to wgpu matrix. */
 pub fn calc_px_to_wgpu_matrix(width: f32, height: f32) -> [[f32; 4]; 4] {
-    return [
+    [
         [2.0 / width, 0.0, 0.0, 0.0],
         [0.0, -2.0 / height, 0.0, 0.0],
         [0.0, 0.0, 1.0, 0.0],
         [-1.0, 1.0, 0.0, 1.0],
-    ];
+    ]
 }
 
 pub fn get_main_shader() -> wgpu::ShaderModuleDescriptor<'static> {
@@ -153,40 +484,29 @@ pub fn get_main_shader() -> wgpu::ShaderModuleDescriptor<'static> {
 }
 
 #[repr(C)]
-#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Debug, Default, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct PrimitiveRenderModuleUniforms {
     pub window_size: [[f32; 4]; 4],
     pub camera_position: [f32; 4],
 }
 
-impl Default for PrimitiveRenderModuleUniforms {
-    fn default() -> Self {
-        Self {
-            window_size: Default::default(),
-            camera_position: Default::default(),
-        }
-    }
-}
-
 pub fn create_uniform_bind_group(
     layout: &wgpu::BindGroupLayout,
     buffer: &wgpu::Buffer,
     device: &wgpu::Device,
 ) -> wgpu::BindGroup {
-    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
         layout,
         entries: &[wgpu::BindGroupEntry {
             binding: 0,
             resource: buffer.as_entire_binding(),
         }],
         label: Some("Primitive Uniform Bind Group"),
-    });
-
-    bind_group
+    })
 }
 
 pub fn create_uniform_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
-    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
         entries: &[wgpu::BindGroupLayoutEntry {
             binding: 0,
             visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
@@ -198,28 +518,24 @@ pub fn create_uniform_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGrou
             count: None,
         }],
         label: Some("Primitive Uniform Bind Group Layout"),
-    });
-
-    layout
+    })
 }
 
 pub fn create_uniform_buffer(
     uniforms: &PrimitiveRenderModuleUniforms,
     device: &wgpu::Device,
 ) -> wgpu::Buffer {
-    let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
         label: Some("Primitive Index Buffer"),
         contents: bytemuck::cast_slice(&[*uniforms]),
         usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-    });
-
-    uniform_buffer
+    })
 }
 
 pub fn create_primitive_mesh_buffers(
     data: &Mesh2D,
     device: &wgpu::Device,
-) -> (wgpu::Buffer, wgpu::Buffer, wgpu::Buffer) {
+) -> (wgpu::Buffer, wgpu::Buffer) {
     let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
         label: Some("Primitive Vertex Buffer"),
         contents: bytemuck::cast_slice(data.0),
@@ -232,48 +548,62 @@ pub fn create_primitive_mesh_buffers(
         usage: wgpu::BufferUsages::INDEX,
     });
 
-    let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+    (vertex_buffer, index_buffer)
+}
+
+/// Creates an instance buffer that can hold `capacity` instances.
+pub fn create_instance_buffer(capacity: usize, device: &wgpu::Device) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
         label: Some("Primitive Instance Buffer"),
         usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-        size: 1048576,
+        size: (capacity * std::mem::size_of::<InstanceData>()) as wgpu::BufferAddress,
         mapped_at_creation: false,
-    });
-
-    //.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-    //    label: Some("Primitive Instance Buffer"),
-    //    contents: bytemuck::cast_slice::<InstanceData, _>(&[]),
-    //    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-    //});
+    })
+}
 
-    (vertex_buffer, index_buffer, instance_buffer)
+/// Which primitives a pipeline draws (see [`PrimitiveOpacity`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrimitivePass {
+    /// Opaque primitives, in the order they were given. They write to the depth buffer, so the primitives in front of them
+    /// can be drawn in any order. Only their antialiased edges are blended, with whatever was drawn before them.
+    Opaque,
+    /// Translucent primitives, which are blended with what's behind them. They don't write to the depth buffer,
+    /// and must be drawn back to front.
+    Translucent,
 }
 
 pub fn create_main_render_pipeline(
     device: &wgpu::Device,
-    shader: wgpu::ShaderModule,
+    shader: &wgpu::ShaderModule,
     config: &SurfaceConfiguration,
-    uniform_bind_group_layout: wgpu::BindGroupLayout,
+    uniform_bind_group_layout: &wgpu::BindGroupLayout,
+    pass: PrimitivePass,
 ) -> wgpu::RenderPipeline {
     let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: Some("Primitive Render Pipeline Layout"),
-        bind_group_layouts: &[&uniform_bind_group_layout],
+        bind_group_layouts: &[uniform_bind_group_layout],
         push_constant_ranges: &[],
     });
 
-    let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-        label: Some("Primitive Render Pipeline"),
+    let (label, vertex_entry_point, depth_write_enabled) = match pass {
+        PrimitivePass::Opaque => ("Opaque Primitive Render Pipeline", "vs_opaque", true),
+        PrimitivePass::Translucent => ("Translucent Primitive Render Pipeline", "vs_main", false),
+    };
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
         layout: Some(&pipeline_layout),
         vertex: wgpu::VertexState {
-            module: &shader,
-            entry_point: "vs_main",
+            module: shader,
+            entry_point: vertex_entry_point,
             buffers: &[Vertex::descriptor(), InstanceData::descriptor()],
         },
         fragment: Some(wgpu::FragmentState {
-            module: &shader,
+            module: shader,
             entry_point: "fs_main",
             targets: &[Some(wgpu::ColorTargetState {
                 format: config.format,
-                blend: Some(wgpu::BlendState::REPLACE),
+                blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
                 write_mask: wgpu::ColorWrites::ALL,
             })],
         }),
@@ -288,7 +618,7 @@ pub fn create_main_render_pipeline(
         },
         depth_stencil: Some(wgpu::DepthStencilState {
             format: wgpu::TextureFormat::Depth32Float,
-            depth_write_enabled: true,
+            depth_write_enabled,
             depth_compare: wgpu::CompareFunction::Less,
             stencil: wgpu::StencilState::default(),
             bias: wgpu::DepthBiasState::default()
@@ -299,6 +629,74 @@ pub fn create_main_render_pipeline(
             alpha_to_coverage_enabled: false,
         },
         multiview: None,
-    });
-    render_pipeline
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn marked(ranges: &[Range<usize>]) -> Vec<Range<usize>> {
+        let mut dirty = DirtyRanges::default();
+        for range in ranges {
+            dirty.mark(range.clone());
+        }
+        dirty.take()
+    }
+
+    #[test]
+    fn disjoint_ranges_stay_apart_and_sorted() {
+        assert_eq!(marked(&[6..8, 0..2, 3..4]), vec![0..2, 3..4, 6..8]);
+    }
+
+    #[test]
+    fn overlapping_and_touching_ranges_merge() {
+        assert_eq!(marked(&[0..4, 2..6]), vec![0..6]);
+        assert_eq!(marked(&[0..4, 4..6]), vec![0..6]);
+        assert_eq!(marked(&[4..6, 0..4]), vec![0..6]);
+        assert_eq!(marked(&[1..3, 0..8]), vec![0..8]);
+    }
+
+    #[test]
+    fn a_range_spanning_several_merges_them_all() {
+        assert_eq!(marked(&[0..1, 3..4, 6..7, 9..10, 2..7]), vec![0..1, 2..7, 9..10]);
+        assert_eq!(marked(&[0..1, 3..4, 6..7, 1..6]), vec![0..7]);
+    }
+
+    #[test]
+    fn empty_ranges_are_ignored() {
+        assert_eq!(marked(&[2..2, 5..5]), Vec::<Range<usize>>::new());
+        assert_eq!(marked(&[0..2, 2..2]), vec![0..2]);
+    }
+
+    #[test]
+    fn the_capacity_doubles_until_everything_fits() {
+        assert_eq!(fitted_capacity(1024, 0, false), 1024);
+        assert_eq!(fitted_capacity(1024, 1024, false), 1024);
+        assert_eq!(fitted_capacity(1024, 1025, false), 2048);
+        assert_eq!(fitted_capacity(1024, 5000, true), 8192);
+    }
+
+    #[test]
+    fn the_capacity_only_shrinks_when_asked_to() {
+        assert_eq!(fitted_capacity(8192, 10, false), 8192);
+        assert_eq!(fitted_capacity(8192, 10, true), INITIAL_INSTANCE_CAPACITY);
+    }
+
+    #[test]
+    fn the_capacity_shrinks_only_while_under_a_quarter_is_used() {
+        // Shrinking at half usage would grow again as soon as a few instances are added.
+        assert_eq!(fitted_capacity(8192, 4096, true), 8192);
+        assert_eq!(fitted_capacity(8192, 2048, true), 8192);
+        assert_eq!(fitted_capacity(8192, 2047, true), 4096);
+        assert_eq!(fitted_capacity(8192, 1000, true), 2048);
+    }
+
+    #[test]
+    fn taking_clears_the_ranges() {
+        let mut dirty = DirtyRanges::default();
+        dirty.mark(0..2);
+        assert_eq!(dirty.take(), vec![0..2]);
+        assert_eq!(dirty.take(), Vec::<Range<usize>>::new());
+    }
 }
@@ -9,20 +9,37 @@ struct InstanceInput {
     @location(7) transform_2: vec4<f32>,
     @location(8) transform_3: vec4<f32>,
     @location(9) i_color: vec4<f32>,
+    @location(10) i_corner_radii: vec4<f32>,
+    @location(11) i_border_color: vec4<f32>,
+    // x: border width, yz: shadow offset, w: shadow blur.
+    @location(12) i_border_shadow: vec4<f32>,
+    @location(13) i_shadow_color: vec4<f32>,
 };
 
 struct VertexOutput {
     @builtin(position) clip_space_position: vec4<f32>,
     @location(0) world_space_position: vec3<f32>,
     @location(1) uv: vec2<f32>,
-    @location(2) color: vec4<f32>, 
+    @location(2) color: vec4<f32>,
+    @location(3) local_position: vec2<f32>,
+    @location(4) size: vec2<f32>,
+    @location(5) corner_radii: vec4<f32>,
+    @location(6) border_color: vec4<f32>,
+    @location(7) border_shadow: vec4<f32>,
+    @location(8) shadow_color: vec4<f32>,
 };
 
 struct FragmentInput {
     @builtin(position) fragment_position: vec4<f32>,
     @location(0) world_space_position: vec3<f32>,
     @location(1) uv: vec2<f32>,
-    @location(2) color: vec4<f32>, 
+    @location(2) color: vec4<f32>,
+    @location(3) local_position: vec2<f32>,
+    @location(4) size: vec2<f32>,
+    @location(5) corner_radii: vec4<f32>,
+    @location(6) border_color: vec4<f32>,
+    @location(7) border_shadow: vec4<f32>,
+    @location(8) shadow_color: vec4<f32>,
 };
 
 struct Uniforms {
@@ -36,11 +53,37 @@ struct Uniforms {
 @group(0) @binding(0)
 var<uniform> uniforms: Uniforms;
 
+// Opaque primitives are drawn (and write their depth) first, then translucent ones are blended back to front.
+// Each primitive is drawn in only one of the passes: see `PrimitiveOpacity` in ui.rs, which this must agree with.
+
 @vertex
 fn vs_main(
     in: VertexInput,
     idata: InstanceInput
 ) -> VertexOutput {
+    return vertex(in, idata);
+}
+
+/// Like `vs_main`, but collapses the primitives that aren't opaque, so they aren't drawn.
+@vertex
+fn vs_opaque(
+    in: VertexInput,
+    idata: InstanceInput
+) -> VertexOutput {
+    var out = vertex(in, idata);
+    if !is_opaque(idata) {
+        out.clip_space_position = vec4(0.0, 0.0, 0.0, 1.0);
+    }
+    return out;
+}
+
+fn is_opaque(idata: InstanceInput) -> bool {
+    let border_width = idata.i_border_shadow.x;
+    let opaque_border = border_width <= 0.0 || idata.i_border_color.a >= 1.0;
+    return idata.i_color.a >= 1.0 && opaque_border && idata.i_shadow_color.a <= 0.0;
+}
+
+fn vertex(in: VertexInput, idata: InstanceInput) -> VertexOutput {
     var out: VertexOutput;
     let transform = mat4x4<f32>(
         idata.transform_0,
@@ -54,23 +97,89 @@ fn vs_main(
         uniforms.px_to_wgpu_2,
         uniforms.px_to_wgpu_3
     );
+
+    // The quad is grown to fit the drop shadow, if there is one.
+    let size = vec2(length(idata.transform_0.xyz), length(idata.transform_1.xyz));
+    let shadow_offset = idata.i_border_shadow.yz;
+    let shadow_blur = idata.i_border_shadow.w;
+    var padding = 0.0;
+    if idata.i_shadow_color.a > 0.0 {
+        padding = shadow_blur + max(abs(shadow_offset.x), abs(shadow_offset.y)) + 1.0;
+    }
+    let local_position = in.position.xy * (size + 2.0 * padding) - padding;
+    let unit_position = local_position / max(size, vec2(1e-6));
+
     var screen_position = px_to_wgpu * (
-        transform * vec4<f32>(in.position, 1.0) - uniforms.camera_position);
+        transform * vec4<f32>(unit_position, in.position.z, 1.0) - uniforms.camera_position);
     out.clip_space_position = screen_position;
-    out.uv = in.uv;
+    out.uv = unit_position;
     out.color = idata.i_color;
+    out.local_position = local_position - size * 0.5;
+    out.size = size;
+    out.corner_radii = idata.i_corner_radii;
+    out.border_color = idata.i_border_color;
+    out.border_shadow = idata.i_border_shadow;
+    out.shadow_color = idata.i_shadow_color;
     return out;
 }
 
+// Fragments outside of the shape are discarded, so they don't hide what's behind them in the depth buffer.
 @fragment
 fn fs_main(in: FragmentInput) -> @location(0) vec4<f32> {
-    let uv = in.uv;
-    let col = in.color;
-    return col * in.fragment_position.z;
-    //return vec4(uv.x, uv.y, 0.0, 1.0);
+    let color = shade(in);
+    if color.a <= 0.0 {
+        discard;
+    }
+    return color;
+}
+
+/// Color of a fragment of a primitive, with premultiplied alpha.
+fn shade(in: FragmentInput) -> vec4<f32> {
+    let half_size = in.size * 0.5;
+    let p = in.local_position;
+    let border_width = in.border_shadow.x;
+    let shadow_offset = in.border_shadow.yz;
+    let shadow_blur = in.border_shadow.w;
+
+    // Corners are top left, top right, bottom right, bottom left, and y points down.
+    var radius = in.corner_radii.x;
+    if p.x >= 0.0 && p.y < 0.0 {
+        radius = in.corner_radii.y;
+    } else if p.x >= 0.0 && p.y >= 0.0 {
+        radius = in.corner_radii.z;
+    } else if p.x < 0.0 && p.y >= 0.0 {
+        radius = in.corner_radii.w;
+    }
+    radius = clamp(radius, 0.0, min(half_size.x, half_size.y));
+
+    // Coverage of the shape, antialiased over about one pixel.
+    let distance = SDFRect(p, half_size, radius);
+    let coverage = clamp(0.5 - distance, 0.0, 1.0);
+
+    // The border is whatever is inside the shape, but not further than `border_width` from its edge.
+    var inner_coverage = 1.0;
+    if border_width > 0.0 {
+        inner_coverage = clamp(0.5 - (distance + border_width), 0.0, 1.0);
+    }
+    let fill = mix(premultiply(in.border_color), premultiply(in.color), inner_coverage);
+    let shape = fill * coverage;
+
+    var shadow = vec4(0.0);
+    if in.shadow_color.a > 0.0 {
+        let blur = max(shadow_blur, 0.5);
+        let shadow_distance = SDFRect(p - shadow_offset, half_size, radius);
+        shadow = premultiply(in.shadow_color) * (1.0 - smoothstep(-blur, blur, shadow_distance));
+    }
+
+    // The shape goes over its shadow.
+    return shape + shadow * (1.0 - shape.a);
+}
+
+fn premultiply(color: vec4<f32>) -> vec4<f32> {
+    return vec4(color.rgb * color.a, color.a);
 }
 
 fn SDFRect(position: vec2<f32>, halfSize: vec2<f32>, cornerRadius: f32) -> f32 {
    let p = abs(position) - halfSize + cornerRadius;
    return length(vec2(max(p.x, 0.0), max(p.y, 0.0))) + min(max(p.x, p.y), 0.0) - cornerRadius;
-}
\ No newline at end of file
+}
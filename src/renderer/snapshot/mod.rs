@@ -0,0 +1,204 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use crate::error::UIError;
+
+use super::engine::render_engine::RenderingEngine;
+
+/// Environment variable that, when set, makes snapshot comparisons overwrite their golden images.
+pub const UPDATE_SNAPSHOTS_VAR: &str = "UI_COMPOSER_UPDATE_SNAPSHOTS";
+
+/// A rendered frame, as tightly packed RGBA8 pixels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// Options for comparing a snapshot against its golden image.
+#[derive(Debug, Clone)]
+pub struct SnapshotOptions {
+    /// Directory where golden images (and diffs, on failure) are stored.
+    pub golden_dir: PathBuf,
+    /// Maximum difference allowed for each channel of a pixel before it is considered different.
+    pub channel_tolerance: u8,
+    /// How many pixels can differ before the comparison fails.
+    pub max_differing_pixels: usize,
+    /// Whether to overwrite the golden image instead of comparing against it.
+    pub update: bool,
+}
+
+impl Default for SnapshotOptions {
+    fn default() -> Self {
+        Self {
+            golden_dir: PathBuf::from("assets/snapshots"),
+            channel_tolerance: 2,
+            max_differing_pixels: 0,
+            update: std::env::var_os(UPDATE_SNAPSHOTS_VAR).is_some(),
+        }
+    }
+}
+
+/// The result of comparing a snapshot against its golden image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotOutcome {
+    /// The snapshot matched the golden image within the tolerance.
+    Matched,
+    /// Updating was requested, so the snapshot became the golden image.
+    Written(PathBuf),
+    /// The snapshot didn't match the golden image. The diff image highlights the differing pixels in red.
+    Mismatched {
+        differing_pixels: usize,
+        actual_path: PathBuf,
+        diff_path: PathBuf,
+    },
+}
+
+impl Snapshot {
+    /// Renders a frame with a headless engine and reads it back.
+    pub async fn capture(engine: &mut RenderingEngine) -> Result<Self, UIError> {
+        engine.render()?;
+        let pixels = engine.read_frame_rgba().await?;
+
+        Ok(Self {
+            width: engine.gpu.surface_config.width,
+            height: engine.gpu.surface_config.height,
+            pixels,
+        })
+    }
+
+    /// Encodes this snapshot as a PNG image.
+    pub fn encode_png(&self) -> Result<Vec<u8>, UIError> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        writer.finish()?;
+        Ok(bytes)
+    }
+
+    /// Decodes a snapshot from an 8-bit RGB or RGBA PNG image.
+    pub fn decode_png(bytes: &[u8]) -> Result<Self, UIError> {
+        let decoder = png::Decoder::new(bytes);
+        let mut reader = decoder.read_info()?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer)?;
+        buffer.truncate(info.buffer_size());
+
+        let pixels = match (info.color_type, info.bit_depth) {
+            (png::ColorType::Rgba, png::BitDepth::Eight) => buffer,
+            (png::ColorType::Rgb, png::BitDepth::Eight) => buffer
+                .chunks_exact(3)
+                .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 0xFF])
+                .collect(),
+            (color, depth) => return Err(UIError::Png(format!("Unsupported PNG format {:?} {:?}.", color, depth))),
+        };
+
+        Ok(Self {
+            width: info.width,
+            height: info.height,
+            pixels,
+        })
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), UIError> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.encode_png()?)?;
+        Ok(())
+    }
+
+    pub fn load_png(path: impl AsRef<Path>) -> Result<Self, UIError> {
+        Self::decode_png(&fs::read(path)?)
+    }
+
+    /// Counts the pixels that differ from `other` by more than `channel_tolerance` in any channel,
+    /// and builds an image where those pixels are red and the others are a faded copy of `self`.
+    /// Snapshots of different sizes differ in every pixel.
+    pub fn diff(&self, other: &Snapshot, channel_tolerance: u8) -> (usize, Snapshot) {
+        if self.width != other.width || self.height != other.height {
+            let pixel_count = self.pixels.len().max(other.pixels.len()) / 4;
+            let diff = Snapshot {
+                width: self.width,
+                height: self.height,
+                pixels: [0xFF, 0x00, 0x00, 0xFF].repeat(self.pixels.len() / 4),
+            };
+            return (pixel_count, diff);
+        }
+
+        let mut differing_pixels = 0;
+        let pixels = self.pixels
+            .chunks_exact(4)
+            .zip(other.pixels.chunks_exact(4))
+            .flat_map(|(a, b)| {
+                let differs = a.iter()
+                    .zip(b)
+                    .any(|(ca, cb)| ca.abs_diff(*cb) > channel_tolerance);
+
+                if differs {
+                    differing_pixels += 1;
+                    [0xFF, 0x00, 0x00, 0xFF]
+                } else {
+                    let faded = |c: u8| 0xC0 + c / 4;
+                    [faded(a[0]), faded(a[1]), faded(a[2]), 0xFF]
+                }
+            })
+            .collect();
+
+        (differing_pixels, Snapshot { width: self.width, height: self.height, pixels })
+    }
+}
+
+/// Compares a snapshot against the golden image `<golden_dir>/<name>.png`.
+/// On mismatch, writes `<name>.actual.png` and `<name>.diff.png` next to it.
+/// A missing golden image is an error, unless `options.update` is set.
+pub fn compare_snapshot(
+    name: &str,
+    snapshot: &Snapshot,
+    options: &SnapshotOptions,
+) -> Result<SnapshotOutcome, UIError> {
+    let golden_path = options.golden_dir.join(format!("{}.png", name));
+
+    if options.update {
+        snapshot.save_png(&golden_path)?;
+        return Ok(SnapshotOutcome::Written(golden_path));
+    }
+
+    if !golden_path.exists() {
+        return Err(UIError::SnapshotMissing { name: name.to_owned(), golden_path });
+    }
+
+    let golden = Snapshot::load_png(&golden_path)?;
+    let (differing_pixels, diff) = golden.diff(snapshot, options.channel_tolerance);
+
+    if differing_pixels <= options.max_differing_pixels {
+        return Ok(SnapshotOutcome::Matched);
+    }
+
+    let actual_path = options.golden_dir.join(format!("{}.actual.png", name));
+    let diff_path = options.golden_dir.join(format!("{}.diff.png", name));
+    snapshot.save_png(&actual_path)?;
+    diff.save_png(&diff_path)?;
+
+    Ok(SnapshotOutcome::Mismatched { differing_pixels, actual_path, diff_path })
+}
+
+/// Like [`compare_snapshot`], but fails if the snapshot doesn't match its golden image.
+pub fn assert_snapshot(
+    name: &str,
+    snapshot: &Snapshot,
+    options: &SnapshotOptions,
+) -> Result<SnapshotOutcome, UIError> {
+    match compare_snapshot(name, snapshot, options)? {
+        SnapshotOutcome::Mismatched { differing_pixels, diff_path, .. } => Err(UIError::SnapshotMismatch {
+            name: name.to_owned(),
+            differing_pixels,
+            diff_path,
+        }),
+        outcome => Ok(outcome),
+    }
+}
@@ -0,0 +1,543 @@
+use std::{
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use futures_signals::signal::{Mutable, MutableSignalCloned};
+
+use crate::{app::scheduler::FrameScheduler, renderer::formats::vertex::InstanceData};
+
+/// A value that can be animated: interpolated between two values, and moved by a velocity for springs.
+pub trait Animatable: Clone + Send + Sync + 'static {
+    /// The value `t` of the way from `self` to `to`. `t` can leave `0..=1`, when an easing or a spring overshoots.
+    fn lerp(&self, to: &Self, t: f32) -> Self;
+
+    /// `self + other * scale`.
+    fn add_scaled(&self, other: &Self, scale: f32) -> Self;
+
+    /// How far apart two values are, used to tell when a spring comes to rest.
+    fn distance(&self, other: &Self) -> f32;
+}
+
+impl Animatable for f32 {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        self + (to - self) * t
+    }
+
+    fn add_scaled(&self, other: &Self, scale: f32) -> Self {
+        self + other * scale
+    }
+
+    fn distance(&self, other: &Self) -> f32 {
+        (self - other).abs()
+    }
+}
+
+/// Arrays animate component-wise, so positions, sizes, colors and transforms can all be animated.
+impl<T: Animatable, const N: usize> Animatable for [T; N] {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        std::array::from_fn(|i| self[i].lerp(&to[i], t))
+    }
+
+    fn add_scaled(&self, other: &Self, scale: f32) -> Self {
+        std::array::from_fn(|i| self[i].add_scaled(&other[i], scale))
+    }
+
+    fn distance(&self, other: &Self) -> f32 {
+        self.iter()
+            .zip(other)
+            .map(|(a, b)| a.distance(b).powi(2))
+            .sum::<f32>()
+            .sqrt()
+    }
+}
+
+/// Primitives animate every property at once: position and size (through the transform),
+/// colors, corner radii, border and shadow.
+impl Animatable for InstanceData {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        InstanceData {
+            transform: self.transform.lerp(&to.transform, t),
+            color: self.color.lerp(&to.color, t),
+            corner_radii: self.corner_radii.lerp(&to.corner_radii, t),
+            border_color: self.border_color.lerp(&to.border_color, t),
+            border_width: self.border_width.lerp(&to.border_width, t),
+            shadow_offset: self.shadow_offset.lerp(&to.shadow_offset, t),
+            shadow_blur: self.shadow_blur.lerp(&to.shadow_blur, t),
+            shadow_color: self.shadow_color.lerp(&to.shadow_color, t),
+        }
+    }
+
+    fn add_scaled(&self, other: &Self, scale: f32) -> Self {
+        InstanceData {
+            transform: self.transform.add_scaled(&other.transform, scale),
+            color: self.color.add_scaled(&other.color, scale),
+            corner_radii: self.corner_radii.add_scaled(&other.corner_radii, scale),
+            border_color: self.border_color.add_scaled(&other.border_color, scale),
+            border_width: self.border_width.add_scaled(&other.border_width, scale),
+            shadow_offset: self.shadow_offset.add_scaled(&other.shadow_offset, scale),
+            shadow_blur: self.shadow_blur.add_scaled(&other.shadow_blur, scale),
+            shadow_color: self.shadow_color.add_scaled(&other.shadow_color, scale),
+        }
+    }
+
+    fn distance(&self, other: &Self) -> f32 {
+        [
+            self.transform.distance(&other.transform),
+            self.color.distance(&other.color),
+            self.corner_radii.distance(&other.corner_radii),
+            self.border_color.distance(&other.border_color),
+            self.border_width.distance(&other.border_width),
+            self.shadow_offset.distance(&other.shadow_offset),
+            self.shadow_blur.distance(&other.shadow_blur),
+            self.shadow_color.distance(&other.shadow_color),
+        ].distance(&[0.0; 8])
+    }
+}
+
+/// How the progress of a tween maps to the progress of its value.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Easing {
+    Linear,
+    /// Starts slowly and speeds up (cubic).
+    EaseIn,
+    /// Starts quickly and slows down (cubic).
+    EaseOut,
+    /// Starts and ends slowly (cubic).
+    #[default]
+    EaseInOut,
+    /// A CSS-like cubic Bézier curve from (0, 0) to (1, 1), with control points (x1, y1) and (x2, y2).
+    CubicBezier(f32, f32, f32, f32),
+}
+
+impl Easing {
+    /// Maps the progress of a tween, from 0 to 1, to the progress of its value.
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match *self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => if t < 0.5 {
+                4.0 * t * t * t
+            } else {
+                1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+            },
+            Easing::CubicBezier(x1, y1, x2, y2) => {
+                let s = solve_bezier_parameter(x1, x2, t);
+                bezier(y1, y2, s)
+            }
+        }
+    }
+}
+
+/// One coordinate of a cubic Bézier curve from 0 to 1 with control points `p1` and `p2`, at parameter `s`.
+fn bezier(p1: f32, p2: f32, s: f32) -> f32 {
+    let inv = 1.0 - s;
+    3.0 * inv * inv * s * p1 + 3.0 * inv * s * s * p2 + s * s * s
+}
+
+/// Finds the parameter at which the curve's x coordinate is `x`, by bisection.
+/// Control points must have their x within `0..=1` for it to be unique, like in CSS.
+fn solve_bezier_parameter(x1: f32, x2: f32, x: f32) -> f32 {
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..32 {
+        let middle = (low + high) / 2.0;
+        if bezier(x1, x2, middle) < x {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    (low + high) / 2.0
+}
+
+/// Physics of a damped spring pulling a value towards its target.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spring {
+    pub stiffness: f32,
+    pub damping: f32,
+    pub mass: f32,
+    /// How close to its target (and how slow) the value must be for the spring to stop.
+    pub rest_distance: f32,
+}
+
+impl Default for Spring {
+    fn default() -> Self {
+        Self {
+            stiffness: 170.0,
+            damping: 26.0,
+            mass: 1.0,
+            rest_distance: 0.001,
+        }
+    }
+}
+
+/// How an [`Animated`] value moves to a new target.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transition {
+    /// Reaches the target after exactly `duration`, following `easing`.
+    Tween { duration: Duration, easing: Easing },
+    /// Follows a spring, which keeps the value's velocity when the target changes mid-flight.
+    Spring(Spring),
+}
+
+impl Transition {
+    pub fn tween(duration: Duration, easing: Easing) -> Self {
+        Transition::Tween { duration, easing }
+    }
+
+    pub fn spring(spring: Spring) -> Self {
+        Transition::Spring(spring)
+    }
+}
+
+/// Springs are simulated in steps of this length, which keeps them stable whatever the frame rate.
+const SPRING_STEP: f32 = 1.0 / 240.0;
+
+/// Longest time simulated in a single tick, so a stalled app doesn't spend ages catching up.
+const MAX_TICK: Duration = Duration::from_millis(250);
+
+/// The motion of an animated value towards its target.
+enum Motion<T> {
+    Tween {
+        from: T,
+        to: T,
+        start: Instant,
+        duration: Duration,
+        easing: Easing,
+    },
+    Spring {
+        target: T,
+        velocity: T,
+        spring: Spring,
+        last_tick: Instant,
+    },
+}
+
+/// Something the animation clock advances on every frame.
+trait Ticking: Send + Sync {
+    /// Moves the animation to `now`. Returns whether it's still running.
+    fn tick(&self, now: Instant) -> bool;
+}
+
+struct AnimatedInner<T> {
+    value: Mutable<T>,
+    motion: Mutex<Option<Motion<T>>>,
+}
+
+impl<T> AnimatedInner<T> {
+    fn motion(&self) -> MutexGuard<'_, Option<Motion<T>>> {
+        self.motion.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<T: Animatable> Ticking for AnimatedInner<T> {
+    fn tick(&self, now: Instant) -> bool {
+        let mut motion = self.motion();
+        let current = self.value.get_cloned();
+
+        let (value, running) = match motion.as_mut() {
+            None => return false,
+            Some(Motion::Tween { from, to, start, duration, easing }) => {
+                let elapsed = now.saturating_duration_since(*start);
+                if elapsed >= *duration {
+                    (to.clone(), false)
+                } else {
+                    let t = elapsed.as_secs_f32() / duration.as_secs_f32();
+                    (from.lerp(to, easing.apply(t)), true)
+                }
+            }
+            Some(Motion::Spring { target, velocity, spring, last_tick }) => {
+                let elapsed = now.saturating_duration_since(*last_tick).min(MAX_TICK).as_secs_f32();
+                *last_tick = now;
+
+                let mut value = current;
+                let steps = (elapsed / SPRING_STEP).ceil() as usize;
+                for _ in 0..steps {
+                    let dt = elapsed / steps as f32;
+                    let displacement = value.add_scaled(target, -1.0);
+                    // Semi-implicit Euler: the velocity is updated first, then moves the value.
+                    *velocity = velocity
+                        .add_scaled(&displacement, -spring.stiffness / spring.mass * dt)
+                        .add_scaled(velocity, -spring.damping / spring.mass * dt);
+                    value = value.add_scaled(velocity, dt);
+                }
+
+                let speed = value.add_scaled(velocity, 1.0).distance(&value);
+                if value.distance(target) < spring.rest_distance && speed < spring.rest_distance {
+                    (target.clone(), false)
+                } else {
+                    (value, true)
+                }
+            }
+        };
+
+        if !running {
+            *motion = None;
+        }
+        drop(motion);
+
+        self.value.set(value);
+        running
+    }
+}
+
+/// The clock of an app's animations, which advances every running [`Animated`] value once per frame.
+///
+/// Apps tick their animations right before redrawing, and keep their frame scheduler awake
+/// only while some animation is running. Without an app (like when rendering headlessly),
+/// call [`Animations::tick`] yourself before every render.
+///
+/// Components turn animated values into primitives on their [`crate::ui::reactive::RerenderDriver`]'s task,
+/// which runs alongside the event loop, so the splices of a tick usually reach the screen a frame later.
+/// For the same reason, the final value of an animation is drawn by one more redraw after the clock stops,
+/// which the driver asks for when it produces that value's splices.
+///
+/// Clocks are cheap to clone, and every clone refers to the same clock.
+#[derive(Clone, Default)]
+pub struct Animations {
+    inner: Arc<AnimationsInner>,
+}
+
+#[derive(Default)]
+struct AnimationsInner {
+    running: Mutex<Vec<Arc<dyn Ticking>>>,
+    scheduler: Option<FrameScheduler>,
+}
+
+impl Animations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a clock which keeps `scheduler` awake while any of its animations is running.
+    pub fn with_scheduler(scheduler: FrameScheduler) -> Self {
+        Self {
+            inner: Arc::new(AnimationsInner {
+                running: Mutex::default(),
+                scheduler: Some(scheduler),
+            }),
+        }
+    }
+
+    /// Advances every running animation to `now`. Returns whether any of them is still running.
+    pub fn tick(&self, now: Instant) -> bool {
+        let mut running = self.running();
+        running.retain(|animation| animation.tick(now));
+        let any_running = !running.is_empty();
+        drop(running);
+
+        if any_running {
+            self.invalidate();
+        }
+        any_running
+    }
+
+    /// Whether any animation is running.
+    pub fn is_running(&self) -> bool {
+        !self.running().is_empty()
+    }
+
+    /// Starts ticking `animation`, unless it's already being ticked.
+    fn start(&self, animation: Arc<dyn Ticking>) {
+        let mut running = self.running();
+        let address = Arc::as_ptr(&animation) as *const ();
+        if !running.iter().any(|other| Arc::as_ptr(other) as *const () == address) {
+            running.push(animation);
+        }
+        drop(running);
+        self.invalidate();
+    }
+
+    fn invalidate(&self) {
+        if let Some(scheduler) = &self.inner.scheduler {
+            scheduler.invalidate();
+        }
+    }
+
+    fn running(&self) -> MutexGuard<'_, Vec<Arc<dyn Ticking>>> {
+        self.inner.running.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// A value that moves smoothly to new targets, exposed as a signal.
+///
+/// Map its signal into a component to animate primitives, for example
+/// `animated.signal().map(|primitive| Rerender::primitives(0..1, vec![primitive]))`
+/// for an `Animated<InstanceData>`.
+///
+/// Animated values are cheap to clone, and every clone refers to the same value.
+#[derive(Clone)]
+pub struct Animated<T> {
+    inner: Arc<AnimatedInner<T>>,
+    animations: Animations,
+}
+
+impl<T: Animatable> Animated<T> {
+    /// Creates a value at rest, animated by the clock `animations`.
+    pub fn new(animations: &Animations, value: T) -> Self {
+        Self {
+            inner: Arc::new(AnimatedInner {
+                value: Mutable::new(value),
+                motion: Mutex::new(None),
+            }),
+            animations: animations.clone(),
+        }
+    }
+
+    /// Starts moving the value to `target`, from wherever it currently is.
+    pub fn animate_to(&self, target: T, transition: Transition) {
+        self.animate_to_at(target, transition, Instant::now());
+    }
+
+    /// Like [`Animated::animate_to`], but starting at `now`, for clocks that aren't ticked with the current time.
+    pub fn animate_to_at(&self, target: T, transition: Transition, now: Instant) {
+        let mut motion = self.inner.motion();
+        let current = self.inner.value.get_cloned();
+
+        let next = match transition {
+            Transition::Tween { duration, easing } => Motion::Tween {
+                from: current,
+                to: target,
+                start: now,
+                duration,
+                easing,
+            },
+            Transition::Spring(spring) => {
+                // Retargeted springs keep their momentum. Anything else starts at rest.
+                let velocity = match motion.take() {
+                    Some(Motion::Spring { velocity, .. }) => velocity,
+                    _ => current.add_scaled(&current, -1.0),
+                };
+                Motion::Spring { target, velocity, spring, last_tick: now }
+            }
+        };
+
+        *motion = Some(next);
+        // The clock locks its animations before their motion, so the motion is unlocked before starting.
+        drop(motion);
+
+        self.animations.start(self.inner.clone());
+    }
+
+    /// Jumps to `value` right away, stopping any running animation.
+    pub fn set(&self, value: T) {
+        // The clock drops stopped animations on its next tick.
+        self.inner.motion().take();
+        self.inner.value.set(value);
+    }
+
+    /// The current value.
+    pub fn get(&self) -> T {
+        self.inner.value.get_cloned()
+    }
+
+    /// The value the animation is heading to, which is the current value if it's at rest.
+    pub fn target(&self) -> T {
+        match self.inner.motion().as_ref() {
+            Some(Motion::Tween { to, .. }) => to.clone(),
+            Some(Motion::Spring { target, .. }) => target.clone(),
+            None => self.get(),
+        }
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.inner.motion().is_some()
+    }
+
+    /// A signal of the value, which changes on every frame while it's animating.
+    pub fn signal(&self) -> MutableSignalCloned<T> {
+        self.inner.value.signal_cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(start: Instant, millis: u64) -> Instant {
+        start + Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn tweens_end_exactly_on_their_target() {
+        let animations = Animations::new();
+        let animated = Animated::new(&animations, 0.0f32);
+        let start = Instant::now();
+        animated.animate_to_at(10.0, Transition::tween(Duration::from_millis(100), Easing::Linear), start);
+
+        assert!(animations.tick(millis(start, 50)));
+        assert_eq!(animated.get(), 5.0);
+
+        assert!(!animations.tick(millis(start, 100)));
+        assert_eq!(animated.get(), 10.0);
+        assert!(!animated.is_animating());
+    }
+
+    #[test]
+    fn cubic_beziers_match_their_endpoints() {
+        let curves = [
+            Easing::CubicBezier(0.25, 0.1, 0.25, 1.0),
+            Easing::CubicBezier(0.42, 0.0, 0.58, 1.0),
+            // Overshoots in between, but still starts and ends in place.
+            Easing::CubicBezier(0.3, -0.5, 0.7, 1.5),
+        ];
+        for curve in curves {
+            assert!(curve.apply(0.0).abs() < 1e-6, "{curve:?} starts at {}", curve.apply(0.0));
+            assert!((curve.apply(1.0) - 1.0).abs() < 1e-6, "{curve:?} ends at {}", curve.apply(1.0));
+        }
+
+        // With control points on the diagonal, the curve is linear.
+        let linear = Easing::CubicBezier(1.0 / 3.0, 1.0 / 3.0, 2.0 / 3.0, 2.0 / 3.0);
+        for t in [0.1, 0.25, 0.5, 0.9] {
+            assert!((linear.apply(t) - t).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn retargeted_springs_keep_their_velocity_and_settle() {
+        let animations = Animations::new();
+        let animated = Animated::new(&animations, 0.0f32);
+        let spring = Spring::default();
+        let start = Instant::now();
+        animated.animate_to_at(100.0, Transition::spring(spring), start);
+
+        assert!(animations.tick(millis(start, 50)));
+        let before = animated.get();
+        assert!(before > 0.0);
+
+        // Heading back to 0, the value keeps moving up for a while, instead of turning around at once.
+        animated.animate_to_at(0.0, Transition::spring(spring), millis(start, 50));
+        assert!(animations.tick(millis(start, 58)));
+        assert!(animated.get() > before);
+
+        let mut now = 58;
+        while animations.tick(millis(start, now)) {
+            now += 16;
+            assert!(now < 10_000, "The spring didn't settle.");
+        }
+        assert!(animated.get().distance(&0.0) < spring.rest_distance);
+        assert!(!animated.is_animating());
+    }
+
+    #[test]
+    fn stopped_animations_stop_invalidating_the_scheduler() {
+        let scheduler = FrameScheduler::new();
+        let animations = Animations::with_scheduler(scheduler.clone());
+        let animated = Animated::new(&animations, 0.0f32);
+        let start = Instant::now();
+        animated.animate_to_at(1.0, Transition::tween(Duration::from_millis(100), Easing::EaseInOut), start);
+        assert!(scheduler.take_frame());
+
+        assert!(animations.tick(millis(start, 50)));
+        assert!(scheduler.take_frame());
+
+        assert!(!animations.tick(millis(start, 100)));
+        assert!(!animations.is_running());
+        assert!(!scheduler.take_frame());
+
+        assert!(!animations.tick(millis(start, 150)));
+        assert!(!scheduler.is_invalidated());
+    }
+}
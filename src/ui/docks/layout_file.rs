@@ -0,0 +1,252 @@
+use std::{collections::BTreeMap, path::Path};
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{error::UIError, ui::geometry::FlowDirection};
+
+use super::{
+    workspace::{DockArrangement, DockNode, FloatingStack, PanelId, Workspace},
+    UIFragment, UIFragmentList, UIFragmentSlot,
+};
+
+/// Version of the layout files written by this version of the crate.
+///
+/// - Version 1 files are bare [`DockArrangement`]s, as written by [`DockArrangement::to_ron`],
+///   and load as a single layout named [`DEFAULT_LAYOUT_NAME`].
+/// - Version 2 added named layouts, the fragment trees of panels, and minimum sizes of tab stacks.
+/// - Version 3 added gaps between the children of fragment lists, and maximum sizes of fragment leaves.
+/// - Version 4 added snapping fragment lists to pixels.
+///
+/// Fields added in later versions must have defaults, so files from earlier versions keep loading.
+pub const LAYOUT_FILE_VERSION: u32 = 4;
+
+/// Name of the layout that version 1 files load as.
+pub const DEFAULT_LAYOUT_NAME: &str = "Default";
+
+/// A file of named workspace layouts, stored as RON, which users save their layouts to and which apps load at startup.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LayoutFile {
+    pub version: u32,
+    pub layouts: Vec<WorkspaceLayout>,
+}
+
+/// A saved workspace: where its panels are docked, and how the contents of its panels are laid out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkspaceLayout {
+    pub name: String,
+    pub arrangement: DockArrangement,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fragments: BTreeMap<PanelId, FragmentNode>,
+}
+
+/// A fragment tree as stored in layout files.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FragmentNode {
+    /// A [`UIFragmentList`].
+    List {
+        direction: FlowDirection,
+        #[serde(default = "default_weight")]
+        weight: f64,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        gap: f64,
+        #[serde(default, skip_serializing_if = "is_false")]
+        snap_to_pixels: bool,
+        children: Vec<FragmentNode>,
+    },
+    /// A leaf, which loads as a [`UIFragmentSlot`].
+    Leaf {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        #[serde(default = "default_weight")]
+        weight: f64,
+        #[serde(default)]
+        minimum_size: (f64, f64),
+        /// Left out when unbounded both ways.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        maximum_size: Option<(f64, f64)>,
+    },
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+fn is_zero(value: &f64) -> bool {
+    *value == 0.0
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// The fields of a layout file of any version, so it's parsed once whatever its version.
+/// Version 1 files are bare arrangements without a version, so it reads as 0.
+#[derive(Deserialize)]
+struct AnyLayoutFile {
+    #[serde(default)]
+    version: u32,
+    /// Required from version 2 on, so it's only missing from version 1 files.
+    #[serde(default, deserialize_with = "deserialize_some")]
+    layouts: Option<Vec<WorkspaceLayout>>,
+    // Version 1 fields, which aren't written as options.
+    #[serde(default, deserialize_with = "deserialize_some")]
+    root: Option<DockNode>,
+    #[serde(default)]
+    floating: Vec<FloatingStack>,
+}
+
+fn deserialize_some<'de, D: Deserializer<'de>, T: Deserialize<'de>>(deserializer: D) -> Result<Option<T>, D::Error> {
+    T::deserialize(deserializer).map(Some)
+}
+
+impl FragmentNode {
+    /// Describes a fragment tree, keeping what affects its layout: flow directions, weights, gaps, snapping,
+    /// minimum and maximum sizes, and names.
+    pub fn from_fragment(fragment: &UIFragment) -> Self {
+        match fragment {
+            UIFragment::Container(container) => FragmentNode::List {
+                direction: container.direction(),
+                weight: container.weight(),
+                gap: container.gap(),
+                snap_to_pixels: container.snap_to_pixels(),
+                children: container.children().iter().map(FragmentNode::from_fragment).collect(),
+            },
+            UIFragment::Leaf(leaf) => FragmentNode::Leaf {
+                name: leaf.name().map(str::to_owned),
+                weight: leaf.weight(),
+                minimum_size: leaf.minimum_size(),
+                maximum_size: Some(leaf.maximum_size())
+                    .filter(|(width, height)| width.is_finite() || height.is_finite()),
+            },
+        }
+    }
+
+    /// Builds the fragment tree this describes, out of [`UIFragmentList`]s and [`UIFragmentSlot`]s.
+    pub fn to_fragment(&self) -> UIFragment {
+        match self {
+            FragmentNode::List { direction, weight, gap, snap_to_pixels, children } => UIFragment::Container(Box::new(
+                UIFragmentList::new(*direction, children.iter().map(FragmentNode::to_fragment).collect())
+                    .with_weight(*weight)
+                    .with_gap(*gap)
+                    .with_snap_to_pixels(*snap_to_pixels),
+            )),
+            FragmentNode::Leaf { name, weight, minimum_size, maximum_size } => {
+                UIFragment::Leaf(Box::new(UIFragmentSlot {
+                    name: name.clone(),
+                    weight: *weight,
+                    minimum_size: *minimum_size,
+                    maximum_size: maximum_size.unwrap_or((f64::INFINITY, f64::INFINITY)),
+                }))
+            }
+        }
+    }
+}
+
+impl Default for LayoutFile {
+    fn default() -> Self {
+        Self { version: LAYOUT_FILE_VERSION, layouts: Vec::new() }
+    }
+}
+
+impl LayoutFile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads a layout file written by this version of the crate, or an earlier one.
+    pub fn from_ron(source: &str) -> Result<Self, UIError> {
+        let file: AnyLayoutFile = ron::from_str(source).map_err(arrangement_error)?;
+
+        // Saving it again writes the current version.
+        match file.version {
+            0 => {
+                let root = file.root.ok_or_else(|| arrangement_error("the layout file has neither a version nor a root"))?;
+                Ok(Self {
+                    version: LAYOUT_FILE_VERSION,
+                    layouts: vec![WorkspaceLayout {
+                        name: DEFAULT_LAYOUT_NAME.to_owned(),
+                        arrangement: DockArrangement { root, floating: file.floating },
+                        fragments: BTreeMap::new(),
+                    }],
+                })
+            }
+            version if version > LAYOUT_FILE_VERSION => Err(UIError::Arrangement(format!(
+                "the layout file has version {}, but only versions up to {} can be read",
+                version, LAYOUT_FILE_VERSION
+            ))),
+            version => {
+                let layouts = file.layouts.ok_or_else(|| UIError::Arrangement(format!(
+                    "the layout file has version {}, but no layouts", version
+                )))?;
+                Ok(Self { version: LAYOUT_FILE_VERSION, layouts })
+            }
+        }
+    }
+
+    pub fn to_ron(&self) -> Result<String, UIError> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).map_err(arrangement_error)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, UIError> {
+        Self::from_ron(&std::fs::read_to_string(path)?)
+    }
+
+    /// Writes the file, replacing it if it exists.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), UIError> {
+        std::fs::write(path, self.to_ron()?)?;
+        Ok(())
+    }
+
+    pub fn layout(&self, name: &str) -> Option<&WorkspaceLayout> {
+        self.layouts.iter().find(|layout| layout.name == name)
+    }
+
+    /// Adds a layout, replacing the one with the same name if there is one.
+    pub fn set_layout(&mut self, layout: WorkspaceLayout) {
+        match self.layouts.iter_mut().find(|other| other.name == layout.name) {
+            Some(other) => *other = layout,
+            None => self.layouts.push(layout),
+        }
+    }
+
+    pub fn remove_layout(&mut self, name: &str) -> Option<WorkspaceLayout> {
+        let index = self.layouts.iter().position(|layout| layout.name == name)?;
+        Some(self.layouts.remove(index))
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.layouts.iter().map(|layout| layout.name.as_str()).collect()
+    }
+}
+
+impl WorkspaceLayout {
+    /// Describes the fragment tree of a panel, to save it along with the layout.
+    pub fn set_fragment(&mut self, panel: PanelId, fragment: &UIFragment) {
+        self.fragments.insert(panel, FragmentNode::from_fragment(fragment));
+    }
+
+    /// Builds the fragment tree saved for a panel.
+    pub fn fragment(&self, panel: &PanelId) -> Option<UIFragment> {
+        self.fragments.get(panel).map(FragmentNode::to_fragment)
+    }
+}
+
+impl Workspace {
+    /// Describes this workspace as a layout named after it, without any fragment trees.
+    pub fn to_layout(&self) -> WorkspaceLayout {
+        WorkspaceLayout {
+            name: self.name.clone(),
+            arrangement: self.arrangement().clone(),
+            fragments: BTreeMap::new(),
+        }
+    }
+
+    /// Creates a workspace arranged as a saved layout.
+    pub fn from_layout(layout: &WorkspaceLayout) -> Self {
+        Workspace::from_arrangement(layout.name.clone(), layout.arrangement.clone())
+    }
+}
+
+fn arrangement_error<E: std::fmt::Display>(error: E) -> UIError {
+    UIError::Arrangement(error.to_string())
+}
@@ -0,0 +1,152 @@
+use std::fmt::Debug;
+
+use super::geometry::FlowDirection;
+
+pub mod layout_file;
+pub mod workspace;
+
+pub use layout_file::{LayoutFile, WorkspaceLayout};
+pub use workspace::Workspace;
+
+#[derive(Debug)]
+pub enum UIFragment {
+    Container(Box<dyn UIFragmentContainer>),
+    Leaf(Box<dyn UIFragmentLeaf>),
+}
+
+/// A fragment with no children, which takes some space in its parent's layout.
+pub trait UIFragmentLeaf: Debug {
+    /// Smallest size, in pixels, this fragment can be laid out with.
+    fn minimum_size(&self) -> (f64, f64) {
+        (0.0, 0.0)
+    }
+
+    /// Largest size, in pixels, this fragment can be laid out with. Unbounded by default.
+    /// Make it the same as the minimum size for a fixed size fragment.
+    fn maximum_size(&self) -> (f64, f64) {
+        (f64::INFINITY, f64::INFINITY)
+    }
+
+    /// How much of the space left in its parent this fragment takes, relative to its siblings.
+    fn weight(&self) -> f64 {
+        1.0
+    }
+
+    /// Name saved along with layouts (see [`layout_file::FragmentNode`]), to tell what goes in this fragment.
+    fn name(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// A fragment that lays its children out one after the other, along its flow direction.
+pub trait UIFragmentContainer: Debug {
+    fn direction(&self) -> FlowDirection;
+
+    fn children(&self) -> &[UIFragment];
+
+    /// How much of the space left in its parent this fragment takes, relative to its siblings.
+    fn weight(&self) -> f64 {
+        1.0
+    }
+
+    /// Space, in pixels, between consecutive children.
+    fn gap(&self) -> f64 {
+        0.0
+    }
+
+    /// Whether the children (and gaps) are rounded to whole pixels, so there are no seams between them.
+    /// See [`crate::ui::layout::algorithm_wdivmin::DivisionOptions::snap_to_pixels`].
+    fn snap_to_pixels(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug)]
+pub struct UIFragmentList {
+    pub direction: FlowDirection,
+    pub list: Vec<UIFragment>,
+    pub weight: f64,
+    pub gap: f64,
+    pub snap_to_pixels: bool,
+}
+
+impl UIFragmentList {
+    pub fn new(direction: FlowDirection, list: Vec<UIFragment>) -> Self {
+        Self { direction, list, weight: 1.0, gap: 0.0, snap_to_pixels: false }
+    }
+
+    pub fn with_weight(mut self, weight: f64) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn with_gap(mut self, gap: f64) -> Self {
+        self.gap = gap;
+        self
+    }
+
+    pub fn with_snap_to_pixels(mut self, snap_to_pixels: bool) -> Self {
+        self.snap_to_pixels = snap_to_pixels;
+        self
+    }
+}
+
+impl UIFragmentContainer for UIFragmentList {
+    fn direction(&self) -> FlowDirection {
+        self.direction
+    }
+
+    fn children(&self) -> &[UIFragment] {
+        &self.list
+    }
+
+    fn weight(&self) -> f64 {
+        self.weight
+    }
+
+    fn gap(&self) -> f64 {
+        self.gap
+    }
+
+    fn snap_to_pixels(&self) -> bool {
+        self.snap_to_pixels
+    }
+}
+
+/// A leaf that only takes space, named after what goes in it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UIFragmentSlot {
+    pub name: Option<String>,
+    pub weight: f64,
+    pub minimum_size: (f64, f64),
+    pub maximum_size: (f64, f64),
+}
+
+impl Default for UIFragmentSlot {
+    fn default() -> Self {
+        Self {
+            name: None,
+            weight: 1.0,
+            minimum_size: (0.0, 0.0),
+            maximum_size: (f64::INFINITY, f64::INFINITY),
+        }
+    }
+}
+
+impl UIFragmentLeaf for UIFragmentSlot {
+    fn minimum_size(&self) -> (f64, f64) {
+        self.minimum_size
+    }
+
+    fn maximum_size(&self) -> (f64, f64) {
+        self.maximum_size
+    }
+
+    fn weight(&self) -> f64 {
+        self.weight
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+}
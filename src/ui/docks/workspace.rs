@@ -0,0 +1,910 @@
+use serde::{Deserialize, Serialize};
+use winit::event::MouseButton;
+
+use crate::{
+    error::UIError,
+    ui::{
+        geometry::{Axis, FlowDirection, Rect, WritingDirection},
+        input::UIEvent,
+        layout::algorithm_wdivmin::{wdivminmax, DivisionElement, DivisionOptions},
+    },
+};
+
+/// Identifies a panel in a [`Workspace`]. Saved along with arrangements, so it should stay the same across sessions.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct PanelId(pub String);
+
+impl From<&str> for PanelId {
+    fn from(id: &str) -> Self {
+        PanelId(id.to_owned())
+    }
+}
+
+/// A node of the tree panels are docked in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DockNode {
+    /// Panes laid out one after the other along `direction`, with draggable dividers between them.
+    Split {
+        direction: FlowDirection,
+        children: Vec<DockChild>,
+    },
+    /// Panels stacked in tabs, of which only the active one is shown.
+    Tabs(TabStack),
+}
+
+/// A pane in a split, which takes space according to its weight (see [`wdivminmax`]).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DockChild {
+    pub weight: f64,
+    pub node: DockNode,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct TabStack {
+    pub panels: Vec<PanelId>,
+    /// Index of the panel that is shown.
+    pub active: usize,
+    /// Smallest size of the stack, tab bar included. Uses [`WorkspaceStyle::minimum_pane_size`] if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minimum_size: Option<(f32, f32)>,
+}
+
+/// Panels torn out of the docking tree, floating over it (or in a window of their own).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FloatingStack {
+    pub rect: Rect,
+    pub tabs: TabStack,
+}
+
+/// Where every panel of a workspace is, which is what gets saved between sessions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DockArrangement {
+    pub root: DockNode,
+    pub floating: Vec<FloatingStack>,
+}
+
+impl DockNode {
+    /// A split whose children all have the same weight.
+    pub fn split(direction: FlowDirection, children: Vec<DockNode>) -> Self {
+        DockNode::Split {
+            direction,
+            children: children.into_iter().map(|node| DockChild { weight: 1.0, node }).collect(),
+        }
+    }
+
+    /// A tab stack showing its first panel.
+    pub fn tabs<I: IntoIterator<Item = PanelId>>(panels: I) -> Self {
+        DockNode::Tabs(TabStack { panels: panels.into_iter().collect(), ..Default::default() })
+    }
+}
+
+impl TabStack {
+    pub fn active_panel(&self) -> Option<&PanelId> {
+        self.panels.get(self.active)
+    }
+}
+
+impl DockArrangement {
+    /// Writes the arrangement as RON.
+    pub fn to_ron(&self) -> Result<String, UIError> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| UIError::Arrangement(e.to_string()))
+    }
+
+    pub fn from_ron(source: &str) -> Result<Self, UIError> {
+        ron::from_str(source).map_err(|e| UIError::Arrangement(e.to_string()))
+    }
+}
+
+/// Sizes of the parts of a workspace that aren't panels, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorkspaceStyle {
+    pub divider_thickness: f32,
+    pub tab_bar_height: f32,
+    pub tab_width: f32,
+    /// Smallest size of a tab stack, tab bar included.
+    pub minimum_pane_size: (f32, f32),
+    /// Size of the floating stacks made by tearing panels out.
+    pub floating_size: (f32, f32),
+    /// How far a tab must be dragged before its panel starts moving.
+    pub drag_threshold: f32,
+    /// Rounds panes and dividers to whole pixels, so there are no seams between them. On by default.
+    pub snap_to_pixels: bool,
+}
+
+impl Default for WorkspaceStyle {
+    fn default() -> Self {
+        Self {
+            divider_thickness: 4.0,
+            tab_bar_height: 24.0,
+            tab_width: 96.0,
+            minimum_pane_size: (64.0, 48.0),
+            floating_size: (320.0, 240.0),
+            drag_threshold: 6.0,
+            snap_to_pixels: true,
+        }
+    }
+}
+
+/// Where a tab stack is: a path of child indices from the root of the docking tree, or a floating stack.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum StackLocation {
+    Docked(Vec<usize>),
+    Floating(usize),
+}
+
+/// The side of a pane a panel is dropped on, on the screen. Dropping in the center adds it as a tab.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DockSide {
+    Center,
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+/// Where a dragged panel goes when it's dropped.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DropTarget {
+    Dock { stack: StackLocation, side: DockSide },
+    /// Tears the panel out, into a new floating stack.
+    TearOut { rect: Rect },
+}
+
+/// A tab stack laid out on the screen.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaneLayout {
+    pub stack: StackLocation,
+    pub rect: Rect,
+    pub tab_bar: Rect,
+    /// Where the active panel's contents go.
+    pub content: Rect,
+    pub tabs: Vec<(PanelId, Rect)>,
+    pub active: Option<PanelId>,
+}
+
+/// A divider between two panes of a split, laid out on the screen.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DividerLayout {
+    /// Path of the split from the root of the docking tree.
+    pub split: Vec<usize>,
+    /// The divider is between the children `index` and `index + 1`.
+    pub index: usize,
+    pub rect: Rect,
+    pub axis: Axis,
+    /// Whether the split's children go right to left, or bottom to top.
+    reversed: bool,
+    /// Sizes and minimum sizes of the split's children along `axis`, when it was laid out.
+    sizes: Vec<f64>,
+    minima: Vec<f64>,
+}
+
+/// A workspace laid out on the screen. Floating panes come after docked ones, and are on top of them.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DockLayout {
+    pub panes: Vec<PaneLayout>,
+    pub dividers: Vec<DividerLayout>,
+    /// The writing direction the splits flowed according to.
+    pub writing: WritingDirection,
+}
+
+impl DockLayout {
+    /// The topmost pane under a point.
+    pub fn pane_at(&self, point: (f32, f32)) -> Option<&PaneLayout> {
+        self.panes.iter().rev().find(|pane| pane.rect.contains(point))
+    }
+
+    pub fn divider_at(&self, point: (f32, f32)) -> Option<&DividerLayout> {
+        self.dividers.iter().find(|divider| divider.rect.contains(point))
+    }
+
+    /// The tab under a point, in the topmost pane under it.
+    pub fn tab_at(&self, point: (f32, f32)) -> Option<&PanelId> {
+        self.pane_at(point)?
+            .tabs
+            .iter()
+            .find(|(_, rect)| rect.contains(point))
+            .map(|(panel, _)| panel)
+    }
+
+    /// Where a panel dropped at `point` goes: on the side of the pane under it whose edge is near,
+    /// in its tabs if it's over the middle or the tab bar, or torn out if it's over no pane.
+    pub fn drop_target(&self, point: (f32, f32), style: &WorkspaceStyle) -> DropTarget {
+        let Some(pane) = self.pane_at(point) else {
+            return DropTarget::TearOut {
+                rect: Rect::new(point.0, point.1, style.floating_size.0, style.floating_size.1),
+            };
+        };
+
+        let side = if pane.tab_bar.contains(point) {
+            DockSide::Center
+        } else {
+            // Distances to each edge, relative to the size of the pane.
+            let rect = pane.rect;
+            let edges = [
+                (DockSide::Left, (point.0 - rect.x) / rect.width),
+                (DockSide::Right, (rect.x + rect.width - point.0) / rect.width),
+                (DockSide::Top, (point.1 - rect.y) / rect.height),
+                (DockSide::Bottom, (rect.y + rect.height - point.1) / rect.height),
+            ];
+            edges.into_iter()
+                .filter(|(_, distance)| *distance < 0.25)
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map_or(DockSide::Center, |(side, _)| side)
+        };
+
+        DropTarget::Dock { stack: pane.stack.clone(), side }
+    }
+}
+
+/// What the pointer is doing to a workspace.
+#[derive(Debug, Clone)]
+enum Interaction {
+    Idle,
+    DraggingDivider { divider: DividerLayout, start: (f32, f32) },
+    /// A tab was pressed, but hasn't moved far enough to drag its panel yet.
+    PressingTab { panel: PanelId, start: (f32, f32) },
+    DraggingPanel { panel: PanelId },
+}
+
+/// A set of panels docked in splits and tab stacks, which the user rearranges by dragging
+/// dividers and tabs, like in editors.
+///
+/// Workspaces don't draw anything: lay one out with [`Workspace::layout`], draw its panes and dividers,
+/// and forward the pointer events over it to [`Workspace::handle_event`]. Floating stacks are drawn over
+/// the docked panes, or in windows of their own.
+///
+/// The arrangement of the panels can be saved and restored, see [`Workspace::arrangement`].
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    pub name: String,
+    pub style: WorkspaceStyle,
+    arrangement: DockArrangement,
+    interaction: Interaction,
+}
+
+impl Workspace {
+    pub fn new<S: Into<String>>(name: S, root: DockNode) -> Self {
+        Self::from_arrangement(name, DockArrangement { root, floating: Vec::new() })
+    }
+
+    pub fn from_arrangement<S: Into<String>>(name: S, arrangement: DockArrangement) -> Self {
+        let mut workspace = Self {
+            name: name.into(),
+            style: WorkspaceStyle::default(),
+            arrangement,
+            interaction: Interaction::Idle,
+        };
+        workspace.prune();
+        workspace
+    }
+
+    pub fn arrangement(&self) -> &DockArrangement {
+        &self.arrangement
+    }
+
+    /// Replaces the arrangement of the panels, like with one saved in an earlier session.
+    pub fn set_arrangement(&mut self, arrangement: DockArrangement) {
+        self.arrangement = arrangement;
+        self.interaction = Interaction::Idle;
+        self.prune();
+    }
+
+    pub fn root(&self) -> &DockNode {
+        &self.arrangement.root
+    }
+
+    pub fn floating(&self) -> &[FloatingStack] {
+        &self.arrangement.floating
+    }
+
+    /// Every panel in the workspace, docked ones first.
+    pub fn panels(&self) -> Vec<PanelId> {
+        let mut panels = Vec::new();
+        collect_panels(&self.arrangement.root, &mut panels);
+        for floating in self.arrangement.floating.iter() {
+            panels.extend(floating.tabs.panels.iter().cloned());
+        }
+        panels
+    }
+
+    pub fn contains(&self, panel: &PanelId) -> bool {
+        self.find_panel(panel).is_some()
+    }
+
+    /// The panel being dragged by the user, if any.
+    pub fn dragged_panel(&self) -> Option<&PanelId> {
+        match &self.interaction {
+            Interaction::DraggingPanel { panel } => Some(panel),
+            _ => None,
+        }
+    }
+
+    /// Lays the workspace out inside `rect`. Splits flow according to `writing`, like fragments do.
+    pub fn layout(&self, rect: Rect, writing: WritingDirection) -> DockLayout {
+        let mut layout = DockLayout { writing, ..Default::default() };
+        let mut path = Vec::new();
+        layout_node(&self.arrangement.root, rect, writing, &self.style, &mut path, &mut layout);
+
+        for (index, floating) in self.arrangement.floating.iter().enumerate() {
+            layout.panes.push(layout_tabs(&floating.tabs, StackLocation::Floating(index), floating.rect, &self.style));
+        }
+        layout
+    }
+
+    /// Adds a panel where `target` says, with splits flowing according to `writing` (like in [`Workspace::layout`]).
+    /// Returns false if the workspace already has it.
+    pub fn add_panel(&mut self, panel: PanelId, target: DropTarget, writing: WritingDirection) -> bool {
+        if self.contains(&panel) {
+            return false;
+        }
+        let inserted = self.insert_panel(panel, target, writing);
+        self.prune();
+        inserted
+    }
+
+    /// Moves a panel where `target` says, like when the user drags it there.
+    /// Splits flow according to `writing`, which should be the one the workspace is laid out with.
+    /// Returns false if the workspace doesn't have the panel, or the target doesn't exist.
+    pub fn move_panel(&mut self, panel: &PanelId, target: DropTarget, writing: WritingDirection) -> bool {
+        // Moving the only panel of a stack to its own side would leave nothing to dock next to.
+        if let DropTarget::Dock { stack, .. } = &target {
+            if self.find_panel(panel).as_ref() == Some(stack)
+                && self.stack(stack).is_some_and(|tabs| tabs.panels.len() == 1)
+            {
+                return false;
+            }
+        }
+
+        let Some(location) = self.find_panel(panel) else {
+            return false;
+        };
+        // Empty stacks are only pruned after inserting, so `target` still points to the same place.
+        remove_from_stack(self.stack_mut(&location).expect("The panel was just found there."), panel);
+        let inserted = self.insert_panel(panel.clone(), target.clone(), writing);
+        if !inserted {
+            // Put it back where it was.
+            self.insert_panel(panel.clone(), DropTarget::Dock { stack: location, side: DockSide::Center }, writing);
+        }
+        self.prune();
+        inserted
+    }
+
+    /// Removes a panel. Returns whether the workspace had it.
+    pub fn close_panel(&mut self, panel: &PanelId) -> bool {
+        let Some(location) = self.find_panel(panel) else {
+            return false;
+        };
+        remove_from_stack(self.stack_mut(&location).expect("The panel was just found there."), panel);
+        self.prune();
+        true
+    }
+
+    /// Shows a panel, making it the active tab of its stack.
+    pub fn activate_panel(&mut self, panel: &PanelId) -> bool {
+        let Some(location) = self.find_panel(panel) else {
+            return false;
+        };
+        let tabs = self.stack_mut(&location).expect("The panel was just found there.");
+        tabs.active = tabs.panels.iter().position(|other| other == panel).unwrap_or(0);
+        true
+    }
+
+    /// Moves a divider by `delta` pixels along its axis, resizing the panes on both of its sides.
+    /// Panes don't shrink below their minimum size. Sizes are taken from when `divider` was laid out,
+    /// so while dragging, pass the divider from when the drag started along with the total distance dragged.
+    pub fn drag_divider(&mut self, divider: &DividerLayout, delta: f32) {
+        let Some(DockNode::Split { children, .. }) = node_at_mut(&mut self.arrangement.root, &divider.split) else {
+            return;
+        };
+        if children.len() != divider.sizes.len() || divider.index + 1 >= children.len() {
+            return;
+        }
+
+        let (before, after) = (divider.index, divider.index + 1);
+        let delta = if divider.reversed { -delta } else { delta } as f64;
+        let delta = delta
+            .max(divider.minima[before] - divider.sizes[before])
+            .min(divider.sizes[after] - divider.minima[after]);
+
+        let mut sizes = divider.sizes.clone();
+        sizes[before] += delta;
+        sizes[after] -= delta;
+
+        // Weights proportional to sizes that respect the minima make wdivminmax give back those sizes.
+        for (child, size) in children.iter_mut().zip(sizes) {
+            child.weight = size.max(f64::EPSILON);
+        }
+    }
+
+    /// Handles a pointer event over the workspace, as laid out in `layout`: dragging dividers,
+    /// switching tabs, and dragging panels to dock them elsewhere or tear them out.
+    /// Returns whether the event changed anything.
+    pub fn handle_event(&mut self, event: &UIEvent, layout: &DockLayout) -> bool {
+        match (event, &self.interaction) {
+            (UIEvent::PointerPressed { position, button: MouseButton::Left }, Interaction::Idle) => {
+                if let Some(divider) = layout.divider_at(*position) {
+                    self.interaction = Interaction::DraggingDivider { divider: divider.clone(), start: *position };
+                    true
+                } else if let Some(panel) = layout.tab_at(*position).cloned() {
+                    self.activate_panel(&panel);
+                    self.interaction = Interaction::PressingTab { panel, start: *position };
+                    true
+                } else {
+                    false
+                }
+            }
+            (UIEvent::PointerMoved { position }, Interaction::DraggingDivider { divider, start }) => {
+                let delta = match divider.axis {
+                    Axis::Horizontal => position.0 - start.0,
+                    Axis::Vertical => position.1 - start.1,
+                };
+                let divider = divider.clone();
+                self.drag_divider(&divider, delta);
+                true
+            }
+            (UIEvent::PointerMoved { position }, Interaction::PressingTab { panel, start }) => {
+                let distance = ((position.0 - start.0).powi(2) + (position.1 - start.1).powi(2)).sqrt();
+                if distance >= self.style.drag_threshold {
+                    self.interaction = Interaction::DraggingPanel { panel: panel.clone() };
+                }
+                false
+            }
+            (UIEvent::PointerReleased { position, button: MouseButton::Left }, interaction) => {
+                let moved = match interaction {
+                    Interaction::DraggingPanel { panel } => {
+                        let panel = panel.clone();
+                        let target = layout.drop_target(*position, &self.style);
+                        self.move_panel(&panel, target, layout.writing)
+                    }
+                    _ => false,
+                };
+                let was_interacting = !matches!(self.interaction, Interaction::Idle);
+                self.interaction = Interaction::Idle;
+                moved || was_interacting
+            }
+            _ => false,
+        }
+    }
+
+    /// Inserts a panel that isn't in any stack. Returns false if the target doesn't exist.
+    fn insert_panel(&mut self, panel: PanelId, target: DropTarget, writing: WritingDirection) -> bool {
+        match target {
+            DropTarget::TearOut { rect } => {
+                self.arrangement.floating.push(FloatingStack {
+                    rect,
+                    tabs: TabStack { panels: vec![panel], ..Default::default() },
+                });
+                true
+            }
+            // Floating stacks are only tabs, so docking on their sides adds tabs too.
+            DropTarget::Dock { stack: StackLocation::Floating(index), .. } => {
+                match self.arrangement.floating.get_mut(index) {
+                    Some(floating) => {
+                        add_tab(&mut floating.tabs, panel);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            DropTarget::Dock { stack: StackLocation::Docked(path), side } => {
+                let Some(node) = node_at_mut(&mut self.arrangement.root, &path) else {
+                    return false;
+                };
+                let DockNode::Tabs(tabs) = node else {
+                    return false;
+                };
+
+                // Whether the panel goes on the left or top of the stack, on the screen.
+                let (direction, first) = match side {
+                    DockSide::Center => {
+                        add_tab(tabs, panel);
+                        return true;
+                    }
+                    DockSide::Left => (FlowDirection::LeftToRight, true),
+                    DockSide::Right => (FlowDirection::LeftToRight, false),
+                    DockSide::Top => (FlowDirection::TopToBottom, true),
+                    DockSide::Bottom => (FlowDirection::TopToBottom, false),
+                };
+                let new_node = DockNode::Tabs(TabStack { panels: vec![panel], ..Default::default() });
+
+                // If the stack already is in a split along the same axis, the panel becomes its sibling,
+                // on the other side of it in the children of splits that flow right to left or bottom to top.
+                if let Some((&index, parent_path)) = path.split_last() {
+                    if let Some(DockNode::Split { direction: parent_direction, children }) =
+                        node_at_mut(&mut self.arrangement.root, parent_path)
+                    {
+                        let parent_basis = parent_direction.resolve(writing);
+                        if parent_basis.axis() == direction.resolve(writing).axis() {
+                            let weight = children[index].weight / 2.0;
+                            children[index].weight = weight;
+                            let position = if first != parent_basis.is_reversed() { index } else { index + 1 };
+                            children.insert(position, DockChild { weight, node: new_node });
+                            return true;
+                        }
+                    }
+                }
+
+                // Otherwise, the stack is split in two, in its own place.
+                let node = node_at_mut(&mut self.arrangement.root, &path).expect("The stack was just found there.");
+                let old_node = std::mem::replace(node, DockNode::tabs([]));
+                let pair = if first { vec![new_node, old_node] } else { vec![old_node, new_node] };
+                *node = DockNode::split(direction, pair);
+                true
+            }
+        }
+    }
+
+    fn find_panel(&self, panel: &PanelId) -> Option<StackLocation> {
+        let mut path = Vec::new();
+        if find_in_node(&self.arrangement.root, panel, &mut path) {
+            return Some(StackLocation::Docked(path));
+        }
+        self.arrangement.floating
+            .iter()
+            .position(|floating| floating.tabs.panels.contains(panel))
+            .map(StackLocation::Floating)
+    }
+
+    fn stack(&self, location: &StackLocation) -> Option<&TabStack> {
+        match location {
+            StackLocation::Docked(path) => match node_at(&self.arrangement.root, path)? {
+                DockNode::Tabs(tabs) => Some(tabs),
+                DockNode::Split { .. } => None,
+            },
+            StackLocation::Floating(index) => self.arrangement.floating.get(*index).map(|floating| &floating.tabs),
+        }
+    }
+
+    fn stack_mut(&mut self, location: &StackLocation) -> Option<&mut TabStack> {
+        match location {
+            StackLocation::Docked(path) => match node_at_mut(&mut self.arrangement.root, path)? {
+                DockNode::Tabs(tabs) => Some(tabs),
+                DockNode::Split { .. } => None,
+            },
+            StackLocation::Floating(index) => self.arrangement.floating.get_mut(*index).map(|floating| &mut floating.tabs),
+        }
+    }
+
+    /// Removes empty stacks, and replaces splits of a single pane by that pane.
+    fn prune(&mut self) {
+        prune_node(&mut self.arrangement.root);
+        self.arrangement.floating.retain(|floating| !floating.tabs.panels.is_empty());
+    }
+}
+
+fn collect_panels(node: &DockNode, panels: &mut Vec<PanelId>) {
+    match node {
+        DockNode::Split { children, .. } => {
+            for child in children.iter() {
+                collect_panels(&child.node, panels);
+            }
+        }
+        DockNode::Tabs(tabs) => panels.extend(tabs.panels.iter().cloned()),
+    }
+}
+
+/// Looks for the stack with a panel, writing its path to `path`.
+fn find_in_node(node: &DockNode, panel: &PanelId, path: &mut Vec<usize>) -> bool {
+    match node {
+        DockNode::Tabs(tabs) => tabs.panels.contains(panel),
+        DockNode::Split { children, .. } => {
+            for (index, child) in children.iter().enumerate() {
+                path.push(index);
+                if find_in_node(&child.node, panel, path) {
+                    return true;
+                }
+                path.pop();
+            }
+            false
+        }
+    }
+}
+
+fn node_at<'a>(node: &'a DockNode, path: &[usize]) -> Option<&'a DockNode> {
+    match (path.split_first(), node) {
+        (None, _) => Some(node),
+        (Some((&index, rest)), DockNode::Split { children, .. }) => node_at(&children.get(index)?.node, rest),
+        (Some(_), DockNode::Tabs(_)) => None,
+    }
+}
+
+fn node_at_mut<'a>(node: &'a mut DockNode, path: &[usize]) -> Option<&'a mut DockNode> {
+    match (path.split_first(), node) {
+        (None, node) => Some(node),
+        (Some((&index, rest)), DockNode::Split { children, .. }) => node_at_mut(&mut children.get_mut(index)?.node, rest),
+        (Some(_), DockNode::Tabs(_)) => None,
+    }
+}
+
+/// Adds a panel as the last tab of a stack, and shows it.
+fn add_tab(tabs: &mut TabStack, panel: PanelId) {
+    tabs.panels.push(panel);
+    tabs.active = tabs.panels.len() - 1;
+}
+
+fn remove_from_stack(tabs: &mut TabStack, panel: &PanelId) {
+    let Some(index) = tabs.panels.iter().position(|other| other == panel) else {
+        return;
+    };
+    tabs.panels.remove(index);
+    // The tabs after the removed one move back, and so does the active one if it was among them.
+    if tabs.active > index || tabs.active >= tabs.panels.len() {
+        tabs.active = tabs.active.saturating_sub(1);
+    }
+}
+
+fn prune_node(node: &mut DockNode) {
+    let DockNode::Split { children, .. } = node else {
+        return;
+    };
+
+    for child in children.iter_mut() {
+        prune_node(&mut child.node);
+    }
+    children.retain(|child| !matches!(&child.node, DockNode::Tabs(tabs) if tabs.panels.is_empty()));
+
+    match children.len() {
+        0 => *node = DockNode::tabs([]),
+        1 => *node = children.pop().expect("There's exactly one child.").node,
+        _ => {}
+    }
+}
+
+/// Smallest size, in pixels, a node can be laid out with.
+fn minimum_size(node: &DockNode, writing: WritingDirection, style: &WorkspaceStyle) -> (f64, f64) {
+    match node {
+        DockNode::Tabs(tabs) => {
+            let (width, height) = tabs.minimum_size.unwrap_or(style.minimum_pane_size);
+            (width as f64, height as f64)
+        }
+        DockNode::Split { direction, children } => {
+            let axis = direction.resolve(writing).axis();
+            let dividers = children.len().saturating_sub(1) as f64 * style.divider_thickness as f64;
+            let (w, h) = children
+                .iter()
+                .map(|child| minimum_size(&child.node, writing, style))
+                .fold((0.0f64, 0.0f64), |(w, h), (child_w, child_h)| match axis {
+                    Axis::Horizontal => (w + child_w, h.max(child_h)),
+                    Axis::Vertical => (w.max(child_w), h + child_h),
+                });
+            match axis {
+                Axis::Horizontal => (w + dividers, h),
+                Axis::Vertical => (w, h + dividers),
+            }
+        }
+    }
+}
+
+fn layout_node(
+    node: &DockNode,
+    rect: Rect,
+    writing: WritingDirection,
+    style: &WorkspaceStyle,
+    path: &mut Vec<usize>,
+    layout: &mut DockLayout,
+) {
+    let (direction, children) = match node {
+        DockNode::Tabs(tabs) => {
+            layout.panes.push(layout_tabs(tabs, StackLocation::Docked(path.clone()), rect, style));
+            return;
+        }
+        DockNode::Split { direction, children } => (direction, children),
+    };
+
+    let basis = direction.resolve(writing);
+    let (axis, reversed) = (basis.axis(), basis.is_reversed());
+    let (flow_start, flow_total) = match axis {
+        Axis::Horizontal => (rect.x, rect.width),
+        Axis::Vertical => (rect.y, rect.height),
+    };
+    let options = DivisionOptions {
+        gap: style.divider_thickness as f64,
+        snap_to_pixels: style.snap_to_pixels,
+    };
+    // Snapping rounds the gaps too.
+    let thickness = if options.snap_to_pixels { options.gap.round() } else { options.gap };
+
+    let minima: Vec<f64> = children.iter()
+        .map(|child| match axis {
+            Axis::Horizontal => minimum_size(&child.node, writing, style).0,
+            Axis::Vertical => minimum_size(&child.node, writing, style).1,
+        })
+        .collect();
+    let elements: Vec<DivisionElement> = children.iter()
+        .zip(minima.iter())
+        .map(|(child, &minimum)| DivisionElement::weighted(child.weight).with_minimum(minimum))
+        .collect();
+    let sizes = wdivminmax(flow_total as f64, &elements, &options);
+
+    // Places a span along the flow axis, from the end of the rectangle if the flow is reversed.
+    let span_rect = |offset: f64, size: f64| {
+        let start = if reversed { flow_total as f64 - offset - size } else { offset };
+        match axis {
+            Axis::Horizontal => Rect::new(flow_start + start as f32, rect.y, size as f32, rect.height),
+            Axis::Vertical => Rect::new(rect.x, flow_start + start as f32, rect.width, size as f32),
+        }
+    };
+
+    let mut offset = 0.0;
+    for (index, (child, size)) in children.iter().zip(sizes.iter()).enumerate() {
+        path.push(index);
+        layout_node(&child.node, span_rect(offset, *size), writing, style, path, layout);
+        path.pop();
+        offset += size;
+
+        if index + 1 < children.len() {
+            layout.dividers.push(DividerLayout {
+                split: path.clone(),
+                index,
+                rect: span_rect(offset, thickness),
+                axis,
+                reversed,
+                sizes: sizes.clone(),
+                minima: minima.clone(),
+            });
+            offset += thickness;
+        }
+    }
+}
+
+fn layout_tabs(tabs: &TabStack, stack: StackLocation, rect: Rect, style: &WorkspaceStyle) -> PaneLayout {
+    let tab_bar_height = style.tab_bar_height.min(rect.height);
+    let tab_bar = Rect::new(rect.x, rect.y, rect.width, tab_bar_height);
+    let content = Rect::new(rect.x, rect.y + tab_bar_height, rect.width, rect.height - tab_bar_height);
+
+    let tabs_rects = tabs.panels
+        .iter()
+        .enumerate()
+        .map(|(index, panel)| {
+            let rect = Rect::new(tab_bar.x + index as f32 * style.tab_width, tab_bar.y, style.tab_width, tab_bar.height);
+            (panel.clone(), rect)
+        })
+        .collect();
+
+    PaneLayout {
+        stack,
+        rect,
+        tab_bar,
+        content,
+        tabs: tabs_rects,
+        active: tabs.active_panel().cloned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn panels(node: &DockNode) -> Vec<PanelId> {
+        let mut panels = Vec::new();
+        collect_panels(node, &mut panels);
+        panels
+    }
+
+    fn pane<'a>(layout: &'a DockLayout, panel: &str) -> &'a PaneLayout {
+        layout.panes.iter().find(|pane| pane.tabs.iter().any(|(id, _)| id.0 == panel)).expect("The panel is laid out.")
+    }
+
+    fn two_panes() -> Workspace {
+        Workspace::new("test", DockNode::split(FlowDirection::Forward, vec![
+            DockNode::tabs(["a".into()]),
+            DockNode::tabs(["b".into(), "c".into()]),
+        ]))
+    }
+
+    #[test]
+    fn docking_on_a_side_adds_a_sibling_in_the_split_along_that_axis() {
+        let mut workspace = two_panes();
+        let target = DropTarget::Dock { stack: StackLocation::Docked(vec![0]), side: DockSide::Left };
+        assert!(workspace.move_panel(&"c".into(), target, WritingDirection::LeftToRight));
+
+        let DockNode::Split { children, .. } = workspace.root() else { panic!("The root is still a split.") };
+        assert_eq!(children.len(), 3);
+        assert_eq!(panels(workspace.root()), vec!["c".into(), "a".into(), "b".into()]);
+    }
+
+    #[test]
+    fn docking_in_right_to_left_splits_lands_on_the_side_it_was_dropped_on() {
+        let mut workspace = two_panes();
+        let writing = WritingDirection::RightToLeft;
+        // "a" is on the right, so its left side is between it and "b".
+        let target = DropTarget::Dock { stack: StackLocation::Docked(vec![0]), side: DockSide::Left };
+        assert!(workspace.move_panel(&"c".into(), target, writing));
+        assert_eq!(panels(workspace.root()), vec!["a".into(), "c".into(), "b".into()]);
+
+        let layout = workspace.layout(Rect::new(0.0, 0.0, 600.0, 300.0), writing);
+        assert!(pane(&layout, "b").rect.x < pane(&layout, "c").rect.x);
+        assert!(pane(&layout, "c").rect.x < pane(&layout, "a").rect.x);
+    }
+
+    #[test]
+    fn docking_across_a_split_splits_the_stack() {
+        let mut workspace = two_panes();
+        let target = DropTarget::Dock { stack: StackLocation::Docked(vec![0]), side: DockSide::Bottom };
+        assert!(workspace.move_panel(&"c".into(), target, WritingDirection::LeftToRight));
+
+        let DockNode::Split { children, .. } = workspace.root() else { panic!("The root is still a split.") };
+        assert_eq!(children[0].node, DockNode::split(FlowDirection::TopToBottom, vec![
+            DockNode::tabs(["a".into()]),
+            DockNode::tabs(["c".into()]),
+        ]));
+    }
+
+    #[test]
+    fn moving_the_only_panel_of_a_stack_beside_itself_does_nothing() {
+        let mut workspace = two_panes();
+        let target = DropTarget::Dock { stack: StackLocation::Docked(vec![0]), side: DockSide::Right };
+        assert!(!workspace.move_panel(&"a".into(), target, WritingDirection::LeftToRight));
+        assert_eq!(workspace.root(), two_panes().root());
+    }
+
+    #[test]
+    fn empty_stacks_and_single_pane_splits_are_pruned() {
+        let workspace = Workspace::from_arrangement("test", DockArrangement {
+            root: DockNode::split(FlowDirection::Forward, vec![
+                DockNode::tabs([]),
+                DockNode::split(FlowDirection::Vertical, vec![DockNode::tabs(["a".into()])]),
+                DockNode::tabs(["b".into()]),
+            ]),
+            floating: vec![FloatingStack { rect: Rect::new(0.0, 0.0, 10.0, 10.0), tabs: TabStack::default() }],
+        });
+
+        assert_eq!(workspace.root(), &DockNode::split(FlowDirection::Forward, vec![
+            DockNode::tabs(["a".into()]),
+            DockNode::tabs(["b".into()]),
+        ]));
+        assert!(workspace.floating().is_empty());
+
+        let mut workspace = workspace;
+        assert!(workspace.close_panel(&"a".into()));
+        assert_eq!(workspace.root(), &DockNode::tabs(["b".into()]));
+    }
+
+    #[test]
+    fn dragged_dividers_stop_at_the_minimum_size_of_panes() {
+        let writing = WritingDirection::LeftToRight;
+        let rect = Rect::new(0.0, 0.0, 400.0, 300.0);
+        let mut workspace = two_panes();
+        let minimum = workspace.style.minimum_pane_size.0;
+
+        let layout = workspace.layout(rect, writing);
+        workspace.drag_divider(&layout.dividers[0], -1000.0);
+        assert_eq!(pane(&workspace.layout(rect, writing), "a").rect.width, minimum);
+
+        workspace.drag_divider(&layout.dividers[0], 1000.0);
+        assert_eq!(pane(&workspace.layout(rect, writing), "b").rect.width, minimum);
+    }
+
+    #[test]
+    fn dividers_of_right_to_left_splits_follow_the_pointer() {
+        let writing = WritingDirection::RightToLeft;
+        let rect = Rect::new(0.0, 0.0, 400.0, 300.0);
+        let mut workspace = two_panes();
+
+        // "a" is on the right, so dragging the divider right shrinks it.
+        let layout = workspace.layout(rect, writing);
+        let width = pane(&layout, "a").rect.width;
+        workspace.drag_divider(&layout.dividers[0], 10.0);
+        assert_eq!(pane(&workspace.layout(rect, writing), "a").rect.width, width - 10.0);
+    }
+
+    #[test]
+    fn drop_targets_depend_on_the_part_of_the_pane_under_the_pointer() {
+        let workspace = Workspace::new("test", DockNode::tabs(["a".into()]));
+        let style = workspace.style;
+        let layout = workspace.layout(Rect::new(0.0, 0.0, 400.0, 300.0), WritingDirection::LeftToRight);
+        let docked = |side| DropTarget::Dock { stack: StackLocation::Docked(vec![]), side };
+
+        assert_eq!(layout.drop_target((200.0, 150.0), &style), docked(DockSide::Center));
+        assert_eq!(layout.drop_target((10.0, 150.0), &style), docked(DockSide::Left));
+        assert_eq!(layout.drop_target((390.0, 150.0), &style), docked(DockSide::Right));
+        assert_eq!(layout.drop_target((200.0, 290.0), &style), docked(DockSide::Bottom));
+        // Over the tab bar, near the top edge, the panel goes in the tabs.
+        assert_eq!(layout.drop_target((200.0, 5.0), &style), docked(DockSide::Center));
+        assert_eq!(
+            layout.drop_target((500.0, 320.0), &style),
+            DropTarget::TearOut { rect: Rect::new(500.0, 320.0, style.floating_size.0, style.floating_size.1) },
+        );
+    }
+}
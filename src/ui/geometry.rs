@@ -1,5 +1,14 @@
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+use serde::{Deserialize, Serialize};
+
 /// Direction of flow, taking into account locales.
-#[derive(Debug, Clone, Copy)]
+///
+/// `Forward` and `Backward` follow the writing direction of the locale: `Forward` is left to right
+/// in English, right to left in Arabic, and top to bottom in vertical Japanese.
+/// `Horizontal` goes the way lines (or columns) follow each other horizontally in the locale,
+/// and `Vertical` is always top to bottom. The rest are the same whatever the locale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum FlowDirection {
     Forward,
     Backward,
@@ -13,29 +22,440 @@ pub enum FlowDirection {
     BottomToTop,
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Direction in which a locale's text is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum WritingDirection {
+    #[default]
+    LeftToRight,
+    RightToLeft,
+    /// Top to bottom, in columns that follow each other from right to left, like Chinese and Japanese.
+    VerticalRightToLeft,
+    /// Top to bottom, in columns that follow each other from left to right, like Mongolian.
+    VerticalLeftToRight,
+}
+
+impl WritingDirection {
+    /// Whether text is written in columns instead of lines.
+    pub fn is_vertical(self) -> bool {
+        matches!(self, WritingDirection::VerticalRightToLeft | WritingDirection::VerticalLeftToRight)
+    }
+
+    /// Whether the start of a line (or the first column) is on the right.
+    pub fn is_right_to_left(self) -> bool {
+        matches!(self, WritingDirection::RightToLeft | WritingDirection::VerticalRightToLeft)
+    }
+}
+
+/// An axis of the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+/// The screen directions a flow direction resolves to, as unit vectors.
+/// The cross axis points down for horizontal flows, and the way the locale's lines (or columns)
+/// follow each other horizontally for vertical ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FlowBasis {
+    pub flow: VectorCartesian,
+    pub cross: VectorCartesian,
+}
+
+impl FlowBasis {
+    /// The screen axis the flow goes along.
+    pub fn axis(&self) -> Axis {
+        if self.flow.x != 0 {
+            Axis::Horizontal
+        } else {
+            Axis::Vertical
+        }
+    }
+
+    /// Whether the flow goes right to left, or bottom to top.
+    pub fn is_reversed(&self) -> bool {
+        self.flow.x + self.flow.y < 0
+    }
+}
+
+impl FlowDirection {
+    /// Resolves this direction to screen directions, for a locale written in `writing`.
+    pub fn resolve(self, writing: WritingDirection) -> FlowBasis {
+        let horizontal = if writing.is_right_to_left() { -VectorCartesian::RIGHT } else { VectorCartesian::RIGHT };
+        let forward = if writing.is_vertical() { VectorCartesian::DOWN } else { horizontal };
+
+        let flow = match self {
+            FlowDirection::Forward => forward,
+            FlowDirection::Backward => -forward,
+            FlowDirection::Horizontal => horizontal,
+            FlowDirection::HorizontalReverse => -horizontal,
+            FlowDirection::Vertical | FlowDirection::TopToBottom => VectorCartesian::DOWN,
+            FlowDirection::VerticalReverse | FlowDirection::BottomToTop => -VectorCartesian::DOWN,
+            FlowDirection::LeftToRight => VectorCartesian::RIGHT,
+            FlowDirection::RightToLeft => -VectorCartesian::RIGHT,
+        };
+        let cross = if flow.x != 0 { VectorCartesian::DOWN } else { horizontal };
+
+        FlowBasis { flow, cross }
+    }
+
+    /// The same direction, flowing the other way.
+    pub fn reversed(self) -> Self {
+        match self {
+            FlowDirection::Forward => FlowDirection::Backward,
+            FlowDirection::Backward => FlowDirection::Forward,
+            FlowDirection::Horizontal => FlowDirection::HorizontalReverse,
+            FlowDirection::HorizontalReverse => FlowDirection::Horizontal,
+            FlowDirection::Vertical => FlowDirection::VerticalReverse,
+            FlowDirection::VerticalReverse => FlowDirection::Vertical,
+            FlowDirection::LeftToRight => FlowDirection::RightToLeft,
+            FlowDirection::RightToLeft => FlowDirection::LeftToRight,
+            FlowDirection::TopToBottom => FlowDirection::BottomToTop,
+            FlowDirection::BottomToTop => FlowDirection::TopToBottom,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum UIVector {
     InFlow(VectorEmbedded),
     Cartesian(VectorCartesian),
 }
 
+impl UIVector {
+    /// Resolves this vector to the basis of the screen, inside a parent flowing in `direction`.
+    pub fn to_cartesian(self, direction: FlowDirection, writing: WritingDirection) -> VectorCartesian {
+        match self {
+            UIVector::InFlow(vector) => vector.to_cartesian(direction, writing),
+            UIVector::Cartesian(vector) => vector,
+        }
+    }
+
+    /// Resolves this vector to the basis of a parent flowing in `direction`.
+    pub fn to_embedded(self, direction: FlowDirection, writing: WritingDirection) -> VectorEmbedded {
+        match self {
+            UIVector::InFlow(vector) => vector,
+            UIVector::Cartesian(vector) => vector.to_embedded(direction, writing),
+        }
+    }
+}
+
+impl From<VectorEmbedded> for UIVector {
+    fn from(vector: VectorEmbedded) -> Self {
+        UIVector::InFlow(vector)
+    }
+}
+
+impl From<VectorCartesian> for UIVector {
+    fn from(vector: VectorCartesian) -> Self {
+        UIVector::Cartesian(vector)
+    }
+}
+
 /// Vector that aligns itself to the basis of the parent's flow direction.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub struct VectorEmbedded {
     flow_axis: i32,
     cross_axis: i32,
 }
 
+impl VectorEmbedded {
+    pub const ZERO: Self = Self::new(0, 0);
+
+    pub const fn new(flow_axis: i32, cross_axis: i32) -> Self {
+        Self { flow_axis, cross_axis }
+    }
+
+    pub fn flow_axis(&self) -> i32 {
+        self.flow_axis
+    }
+
+    pub fn cross_axis(&self) -> i32 {
+        self.cross_axis
+    }
+
+    /// Resolves this vector to the basis of the screen, inside a parent flowing in `direction`.
+    pub fn to_cartesian(self, direction: FlowDirection, writing: WritingDirection) -> VectorCartesian {
+        let basis = direction.resolve(writing);
+        basis.flow * self.flow_axis + basis.cross * self.cross_axis
+    }
+}
+
 /// Vector that aligns itself with the basis of the screen.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub struct VectorCartesian {
     x: i32,
     y: i32,
 }
 
+impl VectorCartesian {
+    pub const ZERO: Self = Self::new(0, 0);
+    pub const RIGHT: Self = Self::new(1, 0);
+    /// Screen coordinates grow downwards.
+    pub const DOWN: Self = Self::new(0, 1);
+
+    pub const fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    pub fn x(&self) -> i32 {
+        self.x
+    }
+
+    pub fn y(&self) -> i32 {
+        self.y
+    }
+
+    pub fn dot(self, other: Self) -> i32 {
+        self.x * other.x + self.y * other.y
+    }
+
+    /// Resolves this vector to the basis of a parent flowing in `direction`.
+    /// This is the inverse of [`VectorEmbedded::to_cartesian`].
+    pub fn to_embedded(self, direction: FlowDirection, writing: WritingDirection) -> VectorEmbedded {
+        // Bases are made of orthogonal unit vectors, so projecting on them inverts the conversion.
+        let basis = direction.resolve(writing);
+        VectorEmbedded::new(self.dot(basis.flow), self.dot(basis.cross))
+    }
+}
+
+/// Implements the arithmetic shared by both kinds of vectors, component-wise.
+macro_rules! impl_vector_ops {
+    ($vector:ident, $a:ident, $b:ident) => {
+        impl Add for $vector {
+            type Output = Self;
+            fn add(self, other: Self) -> Self {
+                Self { $a: self.$a + other.$a, $b: self.$b + other.$b }
+            }
+        }
+
+        impl Sub for $vector {
+            type Output = Self;
+            fn sub(self, other: Self) -> Self {
+                Self { $a: self.$a - other.$a, $b: self.$b - other.$b }
+            }
+        }
+
+        impl Neg for $vector {
+            type Output = Self;
+            fn neg(self) -> Self {
+                Self { $a: -self.$a, $b: -self.$b }
+            }
+        }
+
+        impl Mul<i32> for $vector {
+            type Output = Self;
+            fn mul(self, scale: i32) -> Self {
+                Self { $a: self.$a * scale, $b: self.$b * scale }
+            }
+        }
+
+        impl AddAssign for $vector {
+            fn add_assign(&mut self, other: Self) {
+                *self = *self + other;
+            }
+        }
+
+        impl SubAssign for $vector {
+            fn sub_assign(&mut self, other: Self) {
+                *self = *self - other;
+            }
+        }
+
+        impl From<(i32, i32)> for $vector {
+            fn from(($a, $b): (i32, i32)) -> Self {
+                Self { $a, $b }
+            }
+        }
+    };
+}
+
+impl_vector_ops!(VectorEmbedded, flow_axis, cross_axis);
+impl_vector_ops!(VectorCartesian, x, y);
+
 /// Axis-aligned bounding box specified in UIVectors.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct UIAABB {
     position: UIVector,
     size: UIVector,
 }
+
+impl UIAABB {
+    pub fn new(position: UIVector, size: UIVector) -> Self {
+        Self { position, size }
+    }
+
+    pub fn position(&self) -> UIVector {
+        self.position
+    }
+
+    pub fn size(&self) -> UIVector {
+        self.size
+    }
+
+    /// Resolves this box to pixels, inside a `parent` rectangle flowing in `direction`.
+    ///
+    /// Embedded positions are measured from the corner the parent's flow starts at
+    /// (the top right one for right-to-left flows), and embedded sizes extend along the flow and cross axes.
+    /// Cartesian positions are measured from the top left corner, and cartesian sizes extend right and down.
+    pub fn resolve(&self, parent: Rect, direction: FlowDirection, writing: WritingDirection) -> Rect {
+        let basis = direction.resolve(writing);
+
+        let (origin, offset) = match self.position {
+            UIVector::InFlow(position) => {
+                let corner_x = if basis.flow.x + basis.cross.x < 0 { parent.x + parent.width } else { parent.x };
+                let corner_y = if basis.flow.y + basis.cross.y < 0 { parent.y + parent.height } else { parent.y };
+                ((corner_x, corner_y), position.to_cartesian(direction, writing))
+            }
+            UIVector::Cartesian(position) => ((parent.x, parent.y), position),
+        };
+        let size = self.size.to_cartesian(direction, writing);
+
+        // Sizes pointing left or up extend the box that way from its position.
+        let x = origin.0 + offset.x as f32 + size.x.min(0) as f32;
+        let y = origin.1 + offset.y as f32 + size.y.min(0) as f32;
+        Rect::new(x, y, size.x.abs() as f32, size.y.abs() as f32)
+    }
+}
+
+/// Axis-aligned rectangle in pixels, on the basis of the screen.
+///
+/// Unlike a [`UIAABB`], which is relative to the flow of its parent and needs one to be resolved against,
+/// a `Rect` is already resolved: it's what layouts produce, and what fragments are hit-tested against.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self { x, y, width, height }
+    }
+
+    /// Whether a point is inside this rectangle. The right and bottom edges are exclusive.
+    pub fn contains(&self, point: (f32, f32)) -> bool {
+        point.0 >= self.x && point.0 < self.x + self.width
+            && point.1 >= self.y && point.1 < self.y + self.height
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIRECTIONS: [FlowDirection; 10] = [
+        FlowDirection::Forward,
+        FlowDirection::Backward,
+        FlowDirection::Horizontal,
+        FlowDirection::HorizontalReverse,
+        FlowDirection::Vertical,
+        FlowDirection::VerticalReverse,
+        FlowDirection::LeftToRight,
+        FlowDirection::TopToBottom,
+        FlowDirection::RightToLeft,
+        FlowDirection::BottomToTop,
+    ];
+
+    const WRITINGS: [WritingDirection; 4] = [
+        WritingDirection::LeftToRight,
+        WritingDirection::RightToLeft,
+        WritingDirection::VerticalRightToLeft,
+        WritingDirection::VerticalLeftToRight,
+    ];
+
+    const RIGHT: VectorCartesian = VectorCartesian::RIGHT;
+    const LEFT: VectorCartesian = VectorCartesian::new(-1, 0);
+    const DOWN: VectorCartesian = VectorCartesian::DOWN;
+    const UP: VectorCartesian = VectorCartesian::new(0, -1);
+
+    #[test]
+    fn every_direction_resolves_under_every_writing_direction() {
+        // The flow of each direction, in the order of `WRITINGS`.
+        let table = [
+            (FlowDirection::Forward, [RIGHT, LEFT, DOWN, DOWN]),
+            (FlowDirection::Backward, [LEFT, RIGHT, UP, UP]),
+            (FlowDirection::Horizontal, [RIGHT, LEFT, LEFT, RIGHT]),
+            (FlowDirection::HorizontalReverse, [LEFT, RIGHT, RIGHT, LEFT]),
+            (FlowDirection::Vertical, [DOWN; 4]),
+            (FlowDirection::VerticalReverse, [UP; 4]),
+            (FlowDirection::LeftToRight, [RIGHT; 4]),
+            (FlowDirection::TopToBottom, [DOWN; 4]),
+            (FlowDirection::RightToLeft, [LEFT; 4]),
+            (FlowDirection::BottomToTop, [UP; 4]),
+        ];
+        // The way lines (or columns) follow each other horizontally, in the order of `WRITINGS`.
+        let horizontal = [RIGHT, LEFT, LEFT, RIGHT];
+
+        assert_eq!(table.len(), DIRECTIONS.len());
+        for (direction, flows) in table {
+            for ((writing, flow), horizontal) in WRITINGS.into_iter().zip(flows).zip(horizontal) {
+                let basis = direction.resolve(writing);
+                let cross = if flow.x() != 0 { DOWN } else { horizontal };
+                assert_eq!(basis, FlowBasis { flow, cross }, "{direction:?} in {writing:?}");
+                assert_eq!(basis.axis(), if flow.x() != 0 { Axis::Horizontal } else { Axis::Vertical });
+                assert_eq!(basis.is_reversed(), flow == LEFT || flow == UP, "{direction:?} in {writing:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn reversed_directions_flow_the_other_way() {
+        for direction in DIRECTIONS {
+            assert_eq!(direction.reversed().reversed(), direction);
+            for writing in WRITINGS {
+                let basis = direction.resolve(writing);
+                let reversed = direction.reversed().resolve(writing);
+                assert_eq!(reversed.flow, -basis.flow, "{direction:?} in {writing:?}");
+                assert_eq!(reversed.cross, basis.cross, "{direction:?} in {writing:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn embedded_and_cartesian_vectors_round_trip() {
+        for direction in DIRECTIONS {
+            for writing in WRITINGS {
+                for a in -2..=2 {
+                    for b in -2..=2 {
+                        let embedded = VectorEmbedded::new(a, b);
+                        let cartesian = embedded.to_cartesian(direction, writing);
+                        assert_eq!(cartesian.to_embedded(direction, writing), embedded, "{direction:?} in {writing:?}");
+
+                        let cartesian = VectorCartesian::new(a, b);
+                        let embedded = cartesian.to_embedded(direction, writing);
+                        assert_eq!(embedded.to_cartesian(direction, writing), cartesian, "{direction:?} in {writing:?}");
+
+                        let vector = UIVector::from(cartesian);
+                        assert_eq!(UIVector::from(vector.to_embedded(direction, writing)).to_cartesian(direction, writing), cartesian);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn embedded_boxes_start_at_the_corner_the_flow_starts_at() {
+        let parent = Rect::new(0.0, 0.0, 100.0, 100.0);
+        let aabb = UIAABB::new(VectorEmbedded::new(10, 5).into(), VectorEmbedded::new(20, 30).into());
+
+        assert_eq!(aabb.resolve(parent, FlowDirection::Forward, WritingDirection::LeftToRight), Rect::new(10.0, 5.0, 20.0, 30.0));
+        assert_eq!(aabb.resolve(parent, FlowDirection::Forward, WritingDirection::RightToLeft), Rect::new(70.0, 5.0, 20.0, 30.0));
+        assert_eq!(aabb.resolve(parent, FlowDirection::TopToBottom, WritingDirection::LeftToRight), Rect::new(5.0, 10.0, 30.0, 20.0));
+        assert_eq!(aabb.resolve(parent, FlowDirection::BottomToTop, WritingDirection::LeftToRight), Rect::new(5.0, 70.0, 30.0, 20.0));
+        assert_eq!(aabb.resolve(parent, FlowDirection::Forward, WritingDirection::VerticalRightToLeft), Rect::new(65.0, 10.0, 30.0, 20.0));
+    }
+
+    #[test]
+    fn cartesian_boxes_ignore_the_flow() {
+        let parent = Rect::new(10.0, 20.0, 100.0, 100.0);
+        let aabb = UIAABB::new(VectorCartesian::new(5, 5).into(), VectorCartesian::new(20, 30).into());
+        for direction in DIRECTIONS {
+            for writing in WRITINGS {
+                assert_eq!(aabb.resolve(parent, direction, writing), Rect::new(15.0, 25.0, 20.0, 30.0));
+            }
+        }
+    }
+}
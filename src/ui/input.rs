@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent};
+
+use super::{geometry::Rect, layout::flow::LayoutBox};
+
+/// How many pixels a scroll of one line moves.
+pub const SCROLL_LINE_HEIGHT: f32 = 20.0;
+
+/// Input event, already resolved to the fragment it targets.
+/// Positions are in logical pixels (physical pixels divided by the window's scale factor),
+/// relative to the top left corner of the window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UIEvent {
+    PointerMoved { position: (f32, f32) },
+    PointerPressed { position: (f32, f32), button: MouseButton },
+    PointerReleased { position: (f32, f32), button: MouseButton },
+    Scrolled { position: (f32, f32), delta: (f32, f32) },
+    KeyPressed { key: Option<VirtualKeyCode>, scancode: u32 },
+    KeyReleased { key: Option<VirtualKeyCode>, scancode: u32 },
+    CharacterReceived(char),
+}
+
+impl UIEvent {
+    /// Where the pointer was when the event happened, for events that are routed by hit-testing.
+    pub fn position(&self) -> Option<(f32, f32)> {
+        match self {
+            UIEvent::PointerMoved { position }
+            | UIEvent::PointerPressed { position, .. }
+            | UIEvent::PointerReleased { position, .. }
+            | UIEvent::Scrolled { position, .. } => Some(*position),
+            _ => None,
+        }
+    }
+}
+
+/// Identifies a fragment of the layout given to an [`InputRouter`], by its path in the fragment tree.
+/// It stays the same when the tree is laid out again, as long as the fragment is still there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FragmentId(u64);
+
+/// When a handler runs while an event travels through the fragment tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventPhase {
+    /// On the way down, from the root to the target. Lets ancestors intercept events.
+    Capture,
+    /// On the way up, from the target to the root.
+    Bubble,
+}
+
+/// Information about the event being dispatched, given to handlers.
+#[derive(Debug)]
+pub struct EventContext {
+    /// The fragment the event is aimed at.
+    pub target: FragmentId,
+    /// The fragment whose handler is running.
+    pub current: FragmentId,
+    pub phase: EventPhase,
+    propagation_stopped: bool,
+}
+
+impl EventContext {
+    /// Stops the event from reaching the handlers of other fragments.
+    pub fn stop_propagation(&mut self) {
+        self.propagation_stopped = true;
+    }
+}
+
+pub type EventHandler = Box<dyn FnMut(&UIEvent, &mut EventContext)>;
+
+struct InputTarget {
+    id: FragmentId,
+    parent: Option<FragmentId>,
+    rect: Rect,
+}
+
+/// Routes input events to the handlers registered on UI fragments.
+///
+/// The fragments are the boxes of a layout (see [`crate::ui::layout::flow::layout`]), so they're hit where they're laid out.
+/// Pointer events go to the topmost fragment under the pointer, keyboard events go to the focused fragment.
+/// Either way, the event is first captured by the target's ancestors (from the root down),
+/// then bubbles back up from the target, until a handler stops its propagation.
+pub struct InputRouter {
+    /// Targets in paint order (parents before their children), so later targets are on top of earlier ones.
+    targets: Vec<InputTarget>,
+    /// The id of every fragment path that was asked for, or laid out.
+    ids: HashMap<Vec<usize>, FragmentId>,
+    handlers: HashMap<FragmentId, Vec<(EventPhase, EventHandler)>>,
+    next_id: u64,
+    focused: Option<FragmentId>,
+    pointer_position: (f32, f32),
+    scale_factor: f64,
+}
+
+impl Default for InputRouter {
+    fn default() -> Self {
+        Self {
+            targets: Vec::new(),
+            ids: HashMap::new(),
+            handlers: HashMap::new(),
+            next_id: 0,
+            focused: None,
+            pointer_position: (0.0, 0.0),
+            scale_factor: 1.0,
+        }
+    }
+}
+
+impl InputRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the scale factor of the window, which pointer positions are divided by.
+    /// It's kept up to date by [`WindowEvent::ScaleFactorChanged`].
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+
+    /// Returns the id of the fragment at `path` in the fragment tree, as indices of children from the root.
+    /// The root's path is empty. Handlers can be added to it before the fragment is laid out.
+    pub fn fragment(&mut self, path: &[usize]) -> FragmentId {
+        if let Some(id) = self.ids.get(path) {
+            return *id;
+        }
+
+        let id = FragmentId(self.next_id);
+        self.next_id += 1;
+        self.ids.insert(path.to_vec(), id);
+        id
+    }
+
+    /// Makes the boxes of `layout` the fragments that receive events, in logical pixels.
+    /// Call it whenever the fragment tree is laid out again. Fragments that aren't in it anymore lose their handlers.
+    pub fn set_layout(&mut self, layout: &LayoutBox) {
+        self.targets.clear();
+        let mut path = Vec::new();
+        self.add_targets(layout, None, &mut path);
+
+        let laid_out: Vec<FragmentId> = self.targets.iter().map(|target| target.id).collect();
+        self.ids.retain(|_, id| laid_out.contains(id));
+        self.handlers.retain(|id, _| laid_out.contains(id));
+        if self.focused.is_some_and(|f| !laid_out.contains(&f)) {
+            self.focused = None;
+        }
+    }
+
+    fn add_targets(&mut self, layout: &LayoutBox, parent: Option<FragmentId>, path: &mut Vec<usize>) {
+        let id = self.fragment(path);
+        self.targets.push(InputTarget { id, parent, rect: layout.rect });
+
+        for (index, child) in layout.children.iter().enumerate() {
+            path.push(index);
+            self.add_targets(child, Some(id), path);
+            path.pop();
+        }
+    }
+
+    /// Adds a handler that runs whenever an event passes through `id` in the given phase.
+    pub fn on_event<F>(&mut self, id: FragmentId, phase: EventPhase, handler: F)
+    where
+        F: FnMut(&UIEvent, &mut EventContext) + 'static,
+    {
+        self.handlers.entry(id).or_default().push((phase, Box::new(handler)));
+    }
+
+    /// Gives keyboard focus to a fragment. Pressing the pointer on a fragment also focuses it.
+    pub fn focus(&mut self, id: Option<FragmentId>) {
+        self.focused = id;
+    }
+
+    pub fn focused(&self) -> Option<FragmentId> {
+        self.focused
+    }
+
+    /// Returns the topmost fragment containing `point`.
+    pub fn hit_test(&self, point: (f32, f32)) -> Option<FragmentId> {
+        self.targets
+            .iter()
+            .rev()
+            .find(|target| target.rect.contains(point))
+            .map(|target| target.id)
+    }
+
+    /// Translates a window event and dispatches it. Returns whether any handler received it.
+    pub fn handle_window_event(&mut self, event: &WindowEvent) -> bool {
+        let position = self.pointer_position;
+        let scale_factor = self.scale_factor;
+        let ui_event = match event {
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                self.scale_factor = *scale_factor;
+                return false;
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                let position = position.to_logical::<f32>(scale_factor);
+                self.pointer_position = (position.x, position.y);
+                UIEvent::PointerMoved { position: self.pointer_position }
+            }
+            WindowEvent::MouseInput { state: ElementState::Pressed, button, .. } =>
+                UIEvent::PointerPressed { position, button: *button },
+            WindowEvent::MouseInput { state: ElementState::Released, button, .. } =>
+                UIEvent::PointerReleased { position, button: *button },
+            WindowEvent::MouseWheel { delta, .. } => {
+                let delta = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => (x * SCROLL_LINE_HEIGHT, y * SCROLL_LINE_HEIGHT),
+                    MouseScrollDelta::PixelDelta(d) => {
+                        let d = d.to_logical::<f32>(scale_factor);
+                        (d.x, d.y)
+                    }
+                };
+                UIEvent::Scrolled { position, delta }
+            }
+            WindowEvent::KeyboardInput { input, .. } => match input.state {
+                ElementState::Pressed => UIEvent::KeyPressed { key: input.virtual_keycode, scancode: input.scancode },
+                ElementState::Released => UIEvent::KeyReleased { key: input.virtual_keycode, scancode: input.scancode },
+            },
+            WindowEvent::ReceivedCharacter(c) => UIEvent::CharacterReceived(*c),
+            _ => return false,
+        };
+
+        self.dispatch(&ui_event)
+    }
+
+    /// Dispatches an event to its target fragment and that fragment's ancestors.
+    /// Returns whether any handler received it.
+    pub fn dispatch(&mut self, event: &UIEvent) -> bool {
+        let target = match event.position() {
+            Some(position) => self.hit_test(position),
+            None => self.focused,
+        };
+        let Some(target) = target else {
+            return false;
+        };
+
+        if let UIEvent::PointerPressed { .. } = event {
+            self.focused = Some(target);
+        }
+
+        // The path from the target up to the root.
+        let mut path = vec![target];
+        while let Some(parent) = self.target_index(*path.last().unwrap()).and_then(|i| self.targets[i].parent) {
+            path.push(parent);
+        }
+
+        let mut context = EventContext {
+            target,
+            current: target,
+            phase: EventPhase::Capture,
+            propagation_stopped: false,
+        };
+        let mut handled = false;
+
+        let route: Vec<_> = path.iter().rev()
+            .map(|id| (EventPhase::Capture, *id))
+            .chain(path.iter().map(|id| (EventPhase::Bubble, *id)))
+            .collect();
+
+        for (phase, id) in route {
+            let Some(handlers) = self.handlers.get_mut(&id) else {
+                continue;
+            };
+            context.current = id;
+            context.phase = phase;
+
+            for (handler_phase, handler) in handlers.iter_mut() {
+                if *handler_phase == phase {
+                    handler(event, &mut context);
+                    handled = true;
+                }
+            }
+
+            // Other handlers on the same fragment still run after propagation stops.
+            if context.propagation_stopped {
+                break;
+            }
+        }
+
+        handled
+    }
+
+    fn target_index(&self, id: FragmentId) -> Option<usize> {
+        self.targets.iter().position(|target| target.id == id)
+    }
+}
@@ -1,29 +1,30 @@
 /// Divides a total number of shares for n elements, where the elements can be biased with a weight, or have a minimum share.
 /// It does three dynamic allocations, and runs in O(n * log(n)).
+///
+/// Weights that are negative or not a number count as 0, and elements without weight take their minimum.
 pub fn wdivmin(
     total: f64,
     el_weights: &[f64],
     el_minima: &[f64]
 ) -> Vec<f64> {
     let el_count = el_weights.len();
+    let weight = |i: usize| if el_weights[i] > 0.0 { el_weights[i] } else { 0.0 };
     // Imagine a container with size x on the lim x -> Infinity.
     // In such a container, minimum size doesn't matter.
     // If you shrink this container, eventually *some* element will hit its
-    // minimum size. The elements need to be addressed in the order they hit the minimum size.
+    // minimum size. The elements need to be addressed in the order they hit the minimum size,
+    // which is the order of how much space they need per unit of weight.
+    let ratio = |i: usize| if weight(i) > 0.0 { el_minima[i] / weight(i) } else { f64::INFINITY };
     let mut indices = (0..el_count).collect::<Vec<usize>>();
-    indices.sort_by(| &i_a, &i_b | {
-        let ratio_a = el_minima[i_a] * el_weights[i_a];
-        let ratio_b = el_minima[i_b] * el_weights[i_b];
-        ratio_b.partial_cmp(&ratio_a).unwrap()
-    });
-    let total_weight_count = el_weights.iter().sum::<f64>();
+    indices.sort_by(| &i_a, &i_b | ratio(i_b).total_cmp(&ratio(i_a)));
+    let total_weight_count = (0..el_count).map(weight).sum::<f64>();
     // After that, we know the characteristics of which elements
     // will be taken off the total, so we can pre-calculate the sums of the weights
     // of the remaining objects.
     let remaining_weight_sums = indices.iter()
         .scan(total_weight_count, |acc, i| {
            let result = Some(*acc);
-           *acc -= el_weights[*i];
+           *acc -= weight(*i);
            result
         });
     // Then, each element will calculate how much they take from the total
@@ -32,7 +33,11 @@ pub fn wdivmin(
     let sizes = indices.iter()
         .zip(remaining_weight_sums)
         .scan(total, |space_left, (i, remaining_weight_sum)| {
-            let el_share_count = *space_left * el_weights[*i] / remaining_weight_sum;
+            let el_share_count = if remaining_weight_sum > 0.0 {
+                *space_left * weight(*i) / remaining_weight_sum
+            } else {
+                0.0
+            };
             let size = el_minima[*i].max(el_share_count);
             *space_left -= size;
             Some(size)
@@ -43,4 +48,222 @@ pub fn wdivmin(
         result[*index] = size;
     }
     result
-}
\ No newline at end of file
+}
+
+/// Constraints on the share of one element divided by [`wdivminmax`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DivisionElement {
+    /// How much of the space left this element takes, relative to the others. Elements without weight take their minimum,
+    /// and weights that are negative or not a number count as 0.
+    pub weight: f64,
+    pub minimum: f64,
+    pub maximum: f64,
+}
+
+impl Default for DivisionElement {
+    fn default() -> Self {
+        Self::weighted(1.0)
+    }
+}
+
+impl DivisionElement {
+    pub fn weighted(weight: f64) -> Self {
+        Self { weight, minimum: 0.0, maximum: f64::INFINITY }
+    }
+
+    /// An element that always takes `size`, whatever the space left.
+    pub fn fixed(size: f64) -> Self {
+        Self { weight: 0.0, minimum: size, maximum: size }
+    }
+
+    pub fn with_minimum(mut self, minimum: f64) -> Self {
+        self.minimum = minimum;
+        self
+    }
+
+    pub fn with_maximum(mut self, maximum: f64) -> Self {
+        self.maximum = maximum;
+        self
+    }
+}
+
+/// Options for dividing space with [`wdivminmax`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DivisionOptions {
+    /// Space between consecutive elements, taken out of the total before dividing it.
+    pub gap: f64,
+    /// Rounds the sizes to whole pixels (and the gap too), so elements laid out one after the other
+    /// from a whole pixel start and end on whole pixels. The sizes and gaps still add up to `total`, rounded.
+    pub snap_to_pixels: bool,
+}
+
+/// Divides a total number of shares for n elements, like [`wdivmin`], where the elements can also have a maximum share,
+/// or a fixed one, and be separated by gaps.
+///
+/// If the minima don't fit, elements overflow the total. If the maxima don't fill it, some of it is left unused.
+/// It runs in O(n²) in the worst case, when every element hits a limit on a different pass.
+pub fn wdivminmax(
+    total: f64,
+    elements: &[DivisionElement],
+    options: &DivisionOptions,
+) -> Vec<f64> {
+    let el_count = elements.len();
+    if el_count == 0 {
+        return Vec::new();
+    }
+
+    let gap = if options.snap_to_pixels { options.gap.round() } else { options.gap };
+    let space = (total - gap * (el_count - 1) as f64).max(0.0);
+    // Like in `wdivmin`, weights that are negative or not a number count as 0.
+    let weights: Vec<f64> = elements.iter()
+        .map(|element| if element.weight > 0.0 { element.weight } else { 0.0 })
+        .collect();
+    // A minimum larger than the maximum wins.
+    let limits: Vec<(f64, f64)> = elements.iter()
+        .map(|element| (element.minimum, element.maximum.max(element.minimum)))
+        .collect();
+
+    // Elements are frozen once they hit a limit, and the others divide what's left between them.
+    let mut sizes = vec![0.0; el_count];
+    let mut frozen = vec![false; el_count];
+    for (index, &weight) in weights.iter().enumerate() {
+        if weight <= 0.0 {
+            sizes[index] = limits[index].0;
+            frozen[index] = true;
+        }
+    }
+
+    loop {
+        let free_weight: f64 = (0..el_count).filter(|&i| !frozen[i]).map(|i| weights[i]).sum();
+        if free_weight <= 0.0 {
+            break;
+        }
+        let frozen_space: f64 = (0..el_count).filter(|&i| frozen[i]).map(|i| sizes[i]).sum();
+        let space_left = (space - frozen_space).max(0.0);
+
+        let mut violation = 0.0;
+        for index in (0..el_count).filter(|&i| !frozen[i]) {
+            let share = space_left * weights[index] / free_weight;
+            let (minimum, maximum) = limits[index];
+            let clamped = share.clamp(minimum, maximum);
+            violation += clamped - share;
+            sizes[index] = share;
+        }
+
+        // Like in CSS flexbox: if the elements need more space than they got, the ones below their minimum
+        // are frozen at it, and if they need less, the ones above their maximum are. Then, the rest is divided again.
+        let mut froze_any = false;
+        for index in 0..el_count {
+            if frozen[index] {
+                continue;
+            }
+            let (minimum, maximum) = limits[index];
+            let below = sizes[index] < minimum;
+            let above = sizes[index] > maximum;
+            if (below && violation >= 0.0) || (above && violation <= 0.0) {
+                sizes[index] = sizes[index].clamp(minimum, maximum);
+                frozen[index] = true;
+                froze_any = true;
+            }
+        }
+        if !froze_any {
+            break;
+        }
+    }
+
+    if options.snap_to_pixels {
+        snap_to_pixels(&mut sizes, gap);
+    }
+    sizes
+}
+
+/// Rounds the edges of elements laid out one after the other, instead of their sizes,
+/// so rounding errors don't add up, and the last element ends where the unrounded one would have, rounded.
+/// `gap` must be whole.
+fn snap_to_pixels(sizes: &mut [f64], gap: f64) {
+    let mut offset = 0.0;
+    for size in sizes.iter_mut() {
+        let start = f64::round(offset);
+        let end = f64::round(offset + *size);
+        offset += *size + gap;
+        *size = end - start;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn divide(total: f64, elements: &[DivisionElement]) -> Vec<f64> {
+        wdivminmax(total, elements, &DivisionOptions::default())
+    }
+
+    #[test]
+    fn space_is_divided_by_weight() {
+        let elements = [DivisionElement::weighted(1.0), DivisionElement::weighted(3.0)];
+        assert_eq!(divide(100.0, &elements), vec![25.0, 75.0]);
+    }
+
+    #[test]
+    fn elements_at_their_maximum_leave_the_rest_to_the_others() {
+        let elements = [DivisionElement::weighted(1.0).with_maximum(10.0), DivisionElement::weighted(1.0)];
+        assert_eq!(divide(100.0, &elements), vec![10.0, 90.0]);
+
+        // If every element hits its maximum, the rest of the space is left unused.
+        let elements = [DivisionElement::weighted(1.0).with_maximum(10.0), DivisionElement::weighted(1.0).with_maximum(20.0)];
+        assert_eq!(divide(100.0, &elements), vec![10.0, 20.0]);
+    }
+
+    #[test]
+    fn fixed_elements_take_their_size_whatever_the_space() {
+        let elements = [DivisionElement::fixed(20.0), DivisionElement::weighted(1.0), DivisionElement::weighted(1.0)];
+        assert_eq!(divide(100.0, &elements), vec![20.0, 40.0, 40.0]);
+        assert_eq!(divide(10.0, &elements), vec![20.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn gaps_are_taken_out_before_dividing() {
+        let options = DivisionOptions { gap: 5.0, snap_to_pixels: false };
+        let sizes = wdivminmax(100.0, &[DivisionElement::default(); 3], &options);
+        assert_eq!(sizes, vec![30.0, 30.0, 30.0]);
+    }
+
+    #[test]
+    fn minima_that_dont_fit_overflow() {
+        let elements = [DivisionElement::weighted(1.0).with_minimum(60.0), DivisionElement::weighted(1.0).with_minimum(60.0)];
+        assert_eq!(divide(100.0, &elements), vec![60.0, 60.0]);
+
+        // Elements above their minimum give space to the ones below it.
+        let elements = [DivisionElement::weighted(1.0).with_minimum(70.0), DivisionElement::weighted(1.0)];
+        assert_eq!(divide(100.0, &elements), vec![70.0, 30.0]);
+    }
+
+    #[test]
+    fn weights_that_are_negative_or_not_a_number_count_as_zero() {
+        let elements = [
+            DivisionElement::weighted(f64::NAN).with_minimum(5.0),
+            DivisionElement::weighted(-1.0).with_minimum(3.0),
+            DivisionElement::weighted(1.0),
+        ];
+        assert_eq!(divide(100.0, &elements), vec![5.0, 3.0, 92.0]);
+    }
+
+    #[test]
+    fn snapped_sizes_are_whole_and_add_up_to_the_rounded_total() {
+        let elements = [
+            DivisionElement::weighted(1.0),
+            DivisionElement::weighted(2.0).with_minimum(13.3),
+            DivisionElement::weighted(1.0).with_maximum(17.7),
+            DivisionElement::weighted(3.0),
+        ];
+        for total in [100.0, 100.4, 100.6, 333.3, 999.99] {
+            for gap in [0.0, 2.4, 3.5] {
+                let options = DivisionOptions { gap, snap_to_pixels: true };
+                let sizes = wdivminmax(total, &elements, &options);
+                assert!(sizes.iter().all(|size| size.fract() == 0.0), "{:?}", sizes);
+                let gaps = gap.round() * (elements.len() - 1) as f64;
+                assert_eq!(sizes.iter().sum::<f64>() + gaps, f64::round(total), "{} with gaps of {}", total, gap);
+            }
+        }
+    }
+}
@@ -0,0 +1,289 @@
+use crate::ui::{
+    docks::{UIFragment, UIFragmentContainer},
+    geometry::{Axis, FlowDirection, Rect, WritingDirection},
+};
+
+use super::algorithm_wdivmin::{wdivminmax, DivisionElement, DivisionOptions};
+
+/// The rectangle a fragment was laid out in, along with its children's, in the same order as the fragment tree.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LayoutBox {
+    pub rect: Rect,
+    pub children: Vec<LayoutBox>,
+}
+
+impl LayoutBox {
+    /// Every rectangle in this tree, parents before their children.
+    pub fn flatten(&self) -> Vec<Rect> {
+        let mut rects = vec![self.rect];
+        for child in self.children.iter() {
+            rects.extend(child.flatten());
+        }
+        rects
+    }
+}
+
+/// Resolves a flow direction to the screen axis it flows along, and whether it flows backwards on it.
+fn resolve_flow(direction: FlowDirection, writing: WritingDirection) -> (Axis, bool) {
+    let basis = direction.resolve(writing);
+    (basis.axis(), basis.is_reversed())
+}
+
+/// Space taken by the gaps between a container's children.
+fn gaps(container: &dyn UIFragmentContainer) -> f64 {
+    container.children().len().saturating_sub(1) as f64 * container.gap()
+}
+
+/// Smallest size, in pixels, a fragment can be laid out with.
+/// For containers, that's the sum of their children's along the flow axis, gaps included, and the largest of them across it.
+pub fn minimum_size(fragment: &UIFragment, writing: WritingDirection) -> (f64, f64) {
+    match fragment {
+        UIFragment::Leaf(leaf) => leaf.minimum_size(),
+        UIFragment::Container(container) => {
+            let (axis, _) = resolve_flow(container.direction(), writing);
+            let (w, h) = container.children()
+                .iter()
+                .map(|child| minimum_size(child, writing))
+                .fold((0.0f64, 0.0f64), |(w, h), (child_w, child_h)| match axis {
+                    Axis::Horizontal => (w + child_w, h.max(child_h)),
+                    Axis::Vertical => (w.max(child_w), h + child_h),
+                });
+            match axis {
+                Axis::Horizontal => (w + gaps(container.as_ref()), h),
+                Axis::Vertical => (w, h + gaps(container.as_ref())),
+            }
+        }
+    }
+}
+
+/// Largest size, in pixels, a fragment can be laid out with.
+/// For containers, that's the sum of their children's along the flow axis, gaps included. They're unbounded across it,
+/// since their children are stretched across it.
+pub fn maximum_size(fragment: &UIFragment, writing: WritingDirection) -> (f64, f64) {
+    match fragment {
+        UIFragment::Leaf(leaf) => leaf.maximum_size(),
+        UIFragment::Container(container) => {
+            let (axis, _) = resolve_flow(container.direction(), writing);
+            let children = container.children().iter().map(|child| maximum_size(child, writing));
+            match axis {
+                Axis::Horizontal => (children.map(|(w, _)| w).sum::<f64>() + gaps(container.as_ref()), f64::INFINITY),
+                Axis::Vertical => (f64::INFINITY, children.map(|(_, h)| h).sum::<f64>() + gaps(container.as_ref())),
+            }
+        }
+    }
+}
+
+pub fn weight(fragment: &UIFragment) -> f64 {
+    match fragment {
+        UIFragment::Leaf(leaf) => leaf.weight(),
+        UIFragment::Container(container) => container.weight(),
+    }
+}
+
+/// Lays a fragment tree out inside `rect`.
+///
+/// Containers divide their space along their flow axis between their children, according to
+/// their weights, minimum and maximum sizes and the gaps between them (see [`wdivminmax`]), and stretch them across it.
+/// If there isn't enough space for every child's minimum size, children overflow the container,
+/// and if their maximum sizes don't fill it, space is left at its end.
+/// Containers that snap to pixels round their children's sizes and gaps, see [`UIFragmentContainer::snap_to_pixels`].
+///
+/// Flow directions resolve according to `writing`, the writing direction of the app's locale,
+/// so `Forward` lists are mirrored for right-to-left languages.
+pub fn layout(fragment: &UIFragment, rect: Rect, writing: WritingDirection) -> LayoutBox {
+    let UIFragment::Container(container) = fragment else {
+        return LayoutBox { rect, children: Vec::new() };
+    };
+
+    let children = container.children();
+    let (axis, reversed) = resolve_flow(container.direction(), writing);
+
+    let (flow_start, flow_total) = match axis {
+        Axis::Horizontal => (rect.x, rect.width),
+        Axis::Vertical => (rect.y, rect.height),
+    };
+    let along = |(width, height): (f64, f64)| match axis {
+        Axis::Horizontal => width,
+        Axis::Vertical => height,
+    };
+    let elements: Vec<DivisionElement> = children.iter()
+        .map(|child| {
+            DivisionElement::weighted(weight(child))
+                .with_minimum(along(minimum_size(child, writing)))
+                .with_maximum(along(maximum_size(child, writing)))
+        })
+        .collect();
+    let options = DivisionOptions { gap: container.gap(), snap_to_pixels: container.snap_to_pixels() };
+    let sizes = wdivminmax(flow_total as f64, &elements, &options);
+    // Snapping rounds the gaps too, and the sizes add up to the rounded total,
+    // so reversed lists have to start from the rounded end to stay on whole pixels.
+    let gap = if options.snap_to_pixels { options.gap.round() } else { options.gap };
+    let flow_end = if options.snap_to_pixels { (flow_total as f64).round() } else { flow_total as f64 };
+
+    let mut offset = 0.0;
+    let children = children.iter()
+        .zip(sizes)
+        .map(|(child, size)| {
+            let start = if reversed {
+                flow_end - offset - size
+            } else {
+                offset
+            };
+            offset += size + gap;
+
+            let child_rect = match axis {
+                Axis::Horizontal => Rect::new(flow_start + start as f32, rect.y, size as f32, rect.height),
+                Axis::Vertical => Rect::new(rect.x, flow_start + start as f32, rect.width, size as f32),
+            };
+            layout(child, child_rect, writing)
+        })
+        .collect();
+
+    LayoutBox { rect, children }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ui::docks::{UIFragmentList, UIFragmentSlot};
+
+    use super::*;
+
+    fn slot(weight: f64) -> UIFragment {
+        UIFragment::Leaf(Box::new(UIFragmentSlot { weight, ..Default::default() }))
+    }
+
+    fn fixed(size: f64) -> UIFragment {
+        UIFragment::Leaf(Box::new(UIFragmentSlot {
+            minimum_size: (size, size),
+            maximum_size: (size, size),
+            ..Default::default()
+        }))
+    }
+
+    fn list(list: UIFragmentList) -> UIFragment {
+        UIFragment::Container(Box::new(list))
+    }
+
+    /// The children's rectangles, as `(x, y, width, height)`.
+    fn children(fragment: &UIFragment, rect: Rect, writing: WritingDirection) -> Vec<(f32, f32, f32, f32)> {
+        layout(fragment, rect, writing)
+            .children
+            .iter()
+            .map(|child| (child.rect.x, child.rect.y, child.rect.width, child.rect.height))
+            .collect()
+    }
+
+    #[test]
+    fn children_share_the_space_by_weight() {
+        let fragment = list(UIFragmentList::new(FlowDirection::LeftToRight, vec![slot(1.0), slot(3.0)]));
+        assert_eq!(
+            children(&fragment, Rect::new(10.0, 20.0, 100.0, 50.0), WritingDirection::LeftToRight),
+            vec![(10.0, 20.0, 25.0, 50.0), (35.0, 20.0, 75.0, 50.0)],
+        );
+    }
+
+    #[test]
+    fn vertical_lists_stack_their_children() {
+        let fragment = list(UIFragmentList::new(FlowDirection::TopToBottom, vec![fixed(10.0), slot(1.0)]));
+        assert_eq!(
+            children(&fragment, Rect::new(0.0, 0.0, 40.0, 100.0), WritingDirection::LeftToRight),
+            vec![(0.0, 0.0, 40.0, 10.0), (0.0, 10.0, 40.0, 90.0)],
+        );
+    }
+
+    #[test]
+    fn gaps_separate_the_children() {
+        let fragment = list(UIFragmentList::new(FlowDirection::LeftToRight, vec![slot(1.0), slot(1.0), slot(1.0)]).with_gap(5.0));
+        assert_eq!(
+            children(&fragment, Rect::new(0.0, 0.0, 100.0, 10.0), WritingDirection::LeftToRight),
+            vec![(0.0, 0.0, 30.0, 10.0), (35.0, 0.0, 30.0, 10.0), (70.0, 0.0, 30.0, 10.0)],
+        );
+    }
+
+    #[test]
+    fn nested_lists_are_laid_out_inside_their_rectangle() {
+        let inner = list(UIFragmentList::new(FlowDirection::TopToBottom, vec![slot(1.0), slot(1.0)]));
+        let fragment = list(UIFragmentList::new(FlowDirection::LeftToRight, vec![fixed(20.0), inner]));
+        let laid_out = layout(&fragment, Rect::new(0.0, 0.0, 100.0, 60.0), WritingDirection::LeftToRight);
+
+        assert_eq!(laid_out.flatten(), vec![
+            Rect::new(0.0, 0.0, 100.0, 60.0),
+            Rect::new(0.0, 0.0, 20.0, 60.0),
+            Rect::new(20.0, 0.0, 80.0, 60.0),
+            Rect::new(20.0, 0.0, 80.0, 30.0),
+            Rect::new(20.0, 30.0, 80.0, 30.0),
+        ]);
+    }
+
+    #[test]
+    fn nested_lists_take_their_childrens_minimum_sizes() {
+        let inner = list(UIFragmentList::new(FlowDirection::LeftToRight, vec![fixed(30.0), fixed(20.0)]).with_gap(4.0));
+        assert_eq!(minimum_size(&inner, WritingDirection::LeftToRight), (54.0, 30.0));
+        assert_eq!(maximum_size(&inner, WritingDirection::LeftToRight), (54.0, f64::INFINITY));
+
+        let fragment = list(UIFragmentList::new(FlowDirection::LeftToRight, vec![slot(1.0), inner]));
+        assert_eq!(
+            children(&fragment, Rect::new(0.0, 0.0, 100.0, 30.0), WritingDirection::LeftToRight),
+            vec![(0.0, 0.0, 46.0, 30.0), (46.0, 0.0, 54.0, 30.0)],
+        );
+    }
+
+    #[test]
+    fn reversed_lists_start_at_the_end() {
+        let fragment = list(UIFragmentList::new(FlowDirection::RightToLeft, vec![fixed(10.0), slot(1.0)]).with_gap(5.0));
+        assert_eq!(
+            children(&fragment, Rect::new(0.0, 0.0, 100.0, 10.0), WritingDirection::LeftToRight),
+            vec![(90.0, 0.0, 10.0, 10.0), (0.0, 0.0, 85.0, 10.0)],
+        );
+
+        let fragment = list(UIFragmentList::new(FlowDirection::BottomToTop, vec![fixed(10.0), slot(1.0)]));
+        assert_eq!(
+            children(&fragment, Rect::new(0.0, 0.0, 10.0, 100.0), WritingDirection::LeftToRight),
+            vec![(0.0, 90.0, 10.0, 10.0), (0.0, 0.0, 10.0, 90.0)],
+        );
+    }
+
+    #[test]
+    fn forward_lists_are_mirrored_right_to_left() {
+        let fragment = list(UIFragmentList::new(FlowDirection::Forward, vec![fixed(10.0), slot(1.0)]));
+        let rect = Rect::new(0.0, 0.0, 100.0, 10.0);
+        assert_eq!(
+            children(&fragment, rect, WritingDirection::LeftToRight),
+            vec![(0.0, 0.0, 10.0, 10.0), (10.0, 0.0, 90.0, 10.0)],
+        );
+        assert_eq!(
+            children(&fragment, rect, WritingDirection::RightToLeft),
+            vec![(90.0, 0.0, 10.0, 10.0), (0.0, 0.0, 90.0, 10.0)],
+        );
+
+        // Screen directions aren't mirrored.
+        let fragment = list(UIFragmentList::new(FlowDirection::LeftToRight, vec![fixed(10.0), slot(1.0)]));
+        assert_eq!(
+            children(&fragment, rect, WritingDirection::RightToLeft),
+            vec![(0.0, 0.0, 10.0, 10.0), (10.0, 0.0, 90.0, 10.0)],
+        );
+    }
+
+    #[test]
+    fn snapped_lists_stay_on_whole_pixels() {
+        for direction in [FlowDirection::LeftToRight, FlowDirection::RightToLeft] {
+            for total in [100.0, 100.4, 100.6, 333.3] {
+                let fragment = list(
+                    UIFragmentList::new(direction, vec![slot(1.0), slot(1.0), slot(1.0)])
+                        .with_gap(2.4)
+                        .with_snap_to_pixels(true),
+                );
+                let rects = children(&fragment, Rect::new(0.0, 0.0, total, 10.0), WritingDirection::LeftToRight);
+
+                for &(x, _, width, _) in rects.iter() {
+                    assert_eq!(x.fract(), 0.0, "{direction:?} in {total} starts at {x}");
+                    assert_eq!(width.fract(), 0.0, "{direction:?} in {total} is {width} wide");
+                }
+                // The list fills the rounded total, from one end to the other.
+                let start = rects.iter().map(|r| r.0).fold(f32::INFINITY, f32::min);
+                let end = rects.iter().map(|r| r.0 + r.2).fold(0.0, f32::max);
+                assert_eq!((start, end), (0.0, total.round()), "{direction:?} in {total}");
+            }
+        }
+    }
+}
@@ -1 +1,2 @@
-pub mod algorithm_wdivmin;
\ No newline at end of file
+pub mod algorithm_wdivmin;
+pub mod flow;
\ No newline at end of file
@@ -0,0 +1,73 @@
+use super::geometry::WritingDirection;
+
+/// Languages written right to left, by their ISO 639 code.
+const RIGHT_TO_LEFT_LANGUAGES: &[&str] = &[
+    "ar", "arc", "ckb", "dv", "fa", "he", "iw", "ks", "ps", "sd", "syr", "ug", "ur", "yi",
+];
+
+/// The language of an app, and the direction it's written in.
+///
+/// The writing direction decides which way `Forward` flows go (see [`crate::ui::geometry::FlowDirection`]),
+/// so layouts mirror for right-to-left languages, and how text is aligned and shaped.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Locale {
+    /// BCP 47 language tag, like `en-US` or `ar-EG`.
+    pub language: String,
+    pub direction: WritingDirection,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self::new("en")
+    }
+}
+
+impl Locale {
+    /// Creates a locale for a language tag, written in the usual horizontal direction of its language.
+    /// Use [`Locale::with_direction`] for vertical writing.
+    pub fn new(language: &str) -> Self {
+        let primary = language.split(['-', '_']).next().unwrap_or_default().to_ascii_lowercase();
+        let direction = if RIGHT_TO_LEFT_LANGUAGES.contains(&primary.as_str()) {
+            WritingDirection::RightToLeft
+        } else {
+            WritingDirection::LeftToRight
+        };
+
+        Self { language: language.to_owned(), direction }
+    }
+
+    pub fn with_direction(mut self, direction: WritingDirection) -> Self {
+        self.direction = direction;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directions_come_from_the_primary_language() {
+        for language in ["ar", "ar-EG", "he", "fa_IR", "ur-PK", "ckb", "ckb-IQ", "AR"] {
+            assert_eq!(Locale::new(language).direction, WritingDirection::RightToLeft, "{language}");
+        }
+        for language in ["en", "en-US", "ja", "zh-Hant-TW", "tr", "hu", ""] {
+            assert_eq!(Locale::new(language).direction, WritingDirection::LeftToRight, "{language}");
+        }
+    }
+
+    #[test]
+    fn kurdish_is_only_right_to_left_in_sorani() {
+        // Kurmanji ("ku") is written in the Latin script, Sorani ("ckb") in the Arabic one.
+        assert_eq!(Locale::new("ku").direction, WritingDirection::LeftToRight);
+        assert_eq!(Locale::new("ku-TR").direction, WritingDirection::LeftToRight);
+        assert_eq!(Locale::new("ckb").direction, WritingDirection::RightToLeft);
+    }
+
+    #[test]
+    fn the_language_tag_is_kept_as_is() {
+        let locale = Locale::new("ar-EG").with_direction(WritingDirection::VerticalRightToLeft);
+        assert_eq!(locale.language, "ar-EG");
+        assert_eq!(locale.direction, WritingDirection::VerticalRightToLeft);
+    }
+}
@@ -1,4 +1,8 @@
+pub mod animation;
 pub mod docks;
 pub mod geometry;
+pub mod input;
 pub mod layout;
+pub mod locale;
+pub mod reactive;
 pub mod utils;
\ No newline at end of file
@@ -0,0 +1,257 @@
+use std::{
+    future::Future,
+    ops::Range,
+    pin::Pin,
+    sync::mpsc::{channel, Sender},
+    task::{Context, Poll},
+};
+
+use futures_signals::signal::Signal;
+
+use crate::{
+    app::scheduler::FrameScheduler,
+    error::UIError,
+    renderer::{formats::vertex::InstanceData, modules::ui::{PrimitiveSplice, SpliceReceiver}},
+};
+
+/// A signal of rerenders, which is what components return.
+pub type BoxedRerenderSignal = Pin<Box<dyn Signal<Item = Rerender> + Send>>;
+
+/// A request, emitted by a component, to rerender the primitives it owns in `range`.
+/// Ranges are relative to the start of the range the component owns, so a component doesn't need to know
+/// where it is in the primitive buffer, and must stay within the range it owns.
+pub struct Rerender {
+    pub range: Range<usize>,
+    pub command: RerenderCommand,
+}
+
+pub enum RerenderCommand {
+    /// The range is now owned by a nested component, which will emit its own rerenders.
+    Nested(BoxedRerenderSignal),
+    /// The range is replaced by these primitives.
+    Primitives(Vec<InstanceData>),
+}
+
+impl Rerender {
+    pub fn nested<S>(range: Range<usize>, component: S) -> Self
+    where
+        S: Signal<Item = Rerender> + Send + 'static,
+    {
+        Self { range, command: RerenderCommand::Nested(Box::pin(component)) }
+    }
+
+    pub fn primitives(range: Range<usize>, primitives: Vec<InstanceData>) -> Self {
+        Self { range, command: RerenderCommand::Primitives(primitives) }
+    }
+}
+
+type NodeId = usize;
+
+/// A component that is currently in the tree.
+struct RerenderNode {
+    id: NodeId,
+    parent: Option<NodeId>,
+    /// Absolute indices into the primitive buffer, kept up to date as splices change its length.
+    range: Range<usize>,
+    /// `None` once the component's signal finished. It still owns its range, and its children keep running,
+    /// until its parent rerenders over it.
+    signal: Option<BoxedRerenderSignal>,
+}
+
+/// Future that polls a tree of components, turning their rerenders into primitive splices.
+///
+/// Whenever a component emits, whatever its previous children owned in that range is dropped,
+/// so only the components whose state changed (and their new children) produce splices.
+/// When a splice changes the amount of primitives, the components after it are moved,
+/// and the ones containing it are resized.
+/// Rerenders outside of the range their component owns are dropped, and sent as errors instead of splices.
+/// It finishes when every component has finished, or when the splice receiver is dropped.
+pub struct RerenderDriver {
+    nodes: Vec<RerenderNode>,
+    next_id: NodeId,
+    splice_sender: Sender<Result<PrimitiveSplice, UIError>>,
+    /// Invalidated whenever splices are sent, so they're rendered.
+    scheduler: Option<FrameScheduler>,
+}
+
+impl RerenderDriver {
+    /// Creates a driver for a root component, along with the receiver of the splices it produces.
+    /// Hand the receiver to a [`crate::renderer::modules::ui::PrimitiveRenderModule`].
+    pub fn new<S>(root: S) -> (Self, SpliceReceiver)
+    where
+        S: Signal<Item = Rerender> + Send + 'static,
+    {
+        let (splice_sender, splice_receiver) = channel();
+        let driver = Self {
+            nodes: vec![RerenderNode {
+                id: 0,
+                parent: None,
+                range: 0..usize::MAX,
+                signal: Some(Box::pin(root)),
+            }],
+            next_id: 1,
+            splice_sender,
+            scheduler: None,
+        };
+        (driver, splice_receiver)
+    }
+
+    /// Makes the driver invalidate `scheduler` whenever it produces splices, so the app redraws them.
+    pub fn with_scheduler(mut self, scheduler: FrameScheduler) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    /// Spawns a driver for `root` on the current tokio runtime.
+    /// If there's a `scheduler` (like [`crate::app::UIApp::scheduler`]), it's invalidated whenever the driver produces splices.
+    pub fn spawn<S>(root: S, scheduler: Option<FrameScheduler>) -> SpliceReceiver
+    where
+        S: Signal<Item = Rerender> + Send + 'static,
+    {
+        let (mut driver, splice_receiver) = Self::new(root);
+        driver.scheduler = scheduler;
+        tokio::spawn(driver);
+        splice_receiver
+    }
+
+    /// Applies a rerender emitted by the node at `index`.
+    /// Returns false if nobody is listening to the splices anymore.
+    fn apply(&mut self, index: usize, rerender: Rerender) -> bool {
+        let emitter = self.nodes[index].id;
+        let owned = self.nodes[index].range.clone();
+
+        // Rerenders outside of what the component owns would overwrite its siblings, so they're dropped.
+        if rerender.range.start > rerender.range.end || rerender.range.end > owned.len() {
+            let error = UIError::InvalidRerender { owned, range: rerender.range };
+            return self.splice_sender.send(Err(error)).is_ok();
+        }
+
+        let range = owned.start + rerender.range.start..owned.start + rerender.range.end;
+        self.remove_children_within(emitter, &range);
+
+        match rerender.command {
+            RerenderCommand::Nested(signal) => {
+                self.nodes.push(RerenderNode {
+                    id: self.next_id,
+                    parent: Some(emitter),
+                    range,
+                    signal: Some(signal),
+                });
+                self.next_id += 1;
+                true
+            }
+            RerenderCommand::Primitives(primitives) => {
+                let delta = primitives.len() as isize - range.len() as isize;
+                if delta != 0 {
+                    self.shift_after(emitter, &range, delta);
+                }
+
+                self.splice_sender
+                    .send(Ok(PrimitiveSplice { range, primitives }))
+                    .is_ok()
+            }
+        }
+    }
+
+    /// Accounts for `range` now holding `delta` more primitives, after `emitter` rerendered it:
+    /// `emitter` and its ancestors grow (or shrink) by `delta`, and the nodes after `range` move by `delta`.
+    fn shift_after(&mut self, emitter: NodeId, range: &Range<usize>, delta: isize) {
+        let mut containing = vec![emitter];
+        let mut current = emitter;
+        while let Some(parent) = self.nodes.iter().find(|node| node.id == current).and_then(|node| node.parent) {
+            containing.push(parent);
+            current = parent;
+        }
+
+        let shift = |index: usize| index.saturating_add_signed(delta);
+
+        for node in self.nodes.iter_mut() {
+            if containing.contains(&node.id) {
+                // The root owns everything, so its end doesn't move.
+                if node.range.end != usize::MAX {
+                    node.range.end = shift(node.range.end);
+                }
+            } else if node.range.start >= range.end {
+                node.range = shift(node.range.start)..shift(node.range.end);
+            }
+        }
+    }
+
+    /// Removes the children of `parent` which overlap `range`, along with all of their descendants.
+    fn remove_children_within(&mut self, parent: NodeId, range: &Range<usize>) {
+        let mut doomed: Vec<NodeId> = self.nodes
+            .iter()
+            .filter(|node| node.parent == Some(parent))
+            .filter(|node| node.range.start < range.end && range.start < node.range.end)
+            .map(|node| node.id)
+            .collect();
+
+        // Children are always created after their parents, so a single pass finds every descendant.
+        for node in self.nodes.iter() {
+            if node.parent.is_some_and(|p| doomed.contains(&p)) {
+                doomed.push(node.id);
+            }
+        }
+
+        self.nodes.retain(|node| !doomed.contains(&node.id));
+    }
+}
+
+impl Future for RerenderDriver {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let mut spliced = false;
+
+        let poll = this.poll_nodes(cx, &mut spliced);
+        if spliced {
+            if let Some(scheduler) = &this.scheduler {
+                scheduler.invalidate();
+            }
+        }
+        poll
+    }
+}
+
+impl RerenderDriver {
+    /// Polls every node until none of them changes, noting in `spliced` whether any splice was sent.
+    fn poll_nodes(&mut self, cx: &mut Context<'_>, spliced: &mut bool) -> Poll<()> {
+        let this = self;
+
+        loop {
+            let mut changed = false;
+            let mut index = 0;
+
+            // Nodes removed by `apply` are always after `index`, and new nodes are pushed at the end,
+            // so they still get polled (and emit their initial value) in this pass.
+            while index < this.nodes.len() {
+                let Some(signal) = this.nodes[index].signal.as_mut() else {
+                    index += 1;
+                    continue;
+                };
+                match signal.as_mut().poll_change(cx) {
+                    Poll::Ready(Some(rerender)) => {
+                        *spliced |= matches!(rerender.command, RerenderCommand::Primitives(_));
+                        if !this.apply(index, rerender) {
+                            return Poll::Ready(());
+                        }
+                        changed = true;
+                    }
+                    // Finished components keep their node, so their children still have a parent to be
+                    // moved, resized and removed with.
+                    Poll::Ready(None) => this.nodes[index].signal = None,
+                    Poll::Pending => {}
+                }
+                index += 1;
+            }
+
+            if this.nodes.iter().all(|node| node.signal.is_none()) {
+                return Poll::Ready(());
+            }
+            if !changed {
+                return Poll::Pending;
+            }
+        }
+    }
+}
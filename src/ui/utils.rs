@@ -1,9 +1,9 @@
 #[derive(Debug)]
 pub struct RefStr(std::rc::Rc<str>);
 
-impl Into<std::rc::Rc<str>> for RefStr {
-    fn into(self) -> std::rc::Rc<str> {
-        self.0
+impl From<RefStr> for std::rc::Rc<str> {
+    fn from(value: RefStr) -> Self {
+        value.0
     }
 }
 
@@ -0,0 +1,76 @@
+use std::task::Poll;
+use ui_composer::error::UIError;
+use ui_composer::renderer::engine::render_engine::{HeadlessRenderingEngineDescriptor, RenderingEngine, RenderingEngineGPU};
+use ui_composer::renderer::engine::render_module::RenderModule;
+use ui_composer::renderer::modules::ui::PrimitiveRenderModule;
+
+/// A module that doesn't know how to recreate its GPU resources.
+struct Stubborn;
+
+impl RenderModule for Stubborn {
+    fn prepare_to_render(&mut self, _: &RenderingEngineGPU) -> Result<(), UIError> {
+        Ok(())
+    }
+
+    fn commit_render<'pass>(&'pass self, _: &mut wgpu::RenderPass<'pass>) -> Result<(), UIError> {
+        Ok(())
+    }
+}
+
+async fn engine() -> Result<RenderingEngine, UIError> {
+    RenderingEngine::new_headless(HeadlessRenderingEngineDescriptor {
+        size: (4, 4),
+        ..Default::default()
+    }).await
+}
+
+#[tokio::test]
+async fn lost_devices_are_replaced_without_blocking() -> Result<(), UIError> {
+    let mut engine = engine().await?;
+    let module = Box::new(PrimitiveRenderModule::from_gpu(&engine.gpu)?);
+    engine.add_render_module(module);
+
+    engine.mark_device_lost();
+    assert!(matches!(engine.render(), Err(UIError::DeviceLost)));
+
+    let mut recovered = engine.poll_device_recovery();
+    while recovered.is_pending() {
+        tokio::task::yield_now().await;
+        recovered = engine.poll_device_recovery();
+    }
+    assert!(matches!(recovered, Poll::Ready(Ok(()))));
+    assert!(!engine.is_device_lost());
+    engine.render()
+}
+
+#[tokio::test]
+async fn modules_that_cant_recreate_their_resources_report_it() -> Result<(), UIError> {
+    let mut engine = engine().await?;
+    let handle = engine.add_render_module(Box::new(Stubborn));
+
+    engine.mark_device_lost();
+    match engine.recover_device().await {
+        Err(UIError::RenderModules(errors)) => assert_eq!(errors[0].0, handle),
+        other => panic!("Expected the module to fail, got {:?}", other),
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn uncaptured_gpu_errors_are_queued_instead_of_panicking() -> Result<(), UIError> {
+    let engine = engine().await?;
+
+    // Buffers mapped at creation must have a size that's a multiple of 4.
+    let _buffer = engine.gpu.device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: 3,
+        usage: wgpu::BufferUsages::VERTEX,
+        mapped_at_creation: true,
+    });
+
+    let errors = engine.take_gpu_errors();
+    assert!(matches!(errors.as_slice(), [UIError::Gpu(_)]));
+    assert!(!engine.is_device_lost());
+    assert!(engine.take_gpu_errors().is_empty());
+    Ok(())
+}
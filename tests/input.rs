@@ -0,0 +1,50 @@
+use std::{cell::Cell, rc::Rc};
+use ui_composer::ui::geometry::Rect;
+use ui_composer::ui::input::{EventPhase, InputRouter, UIEvent};
+use ui_composer::ui::layout::flow::LayoutBox;
+use winit::event::MouseButton;
+
+fn screen(button: Rect) -> LayoutBox {
+    LayoutBox {
+        rect: Rect::new(0.0, 0.0, 300.0, 300.0),
+        children: vec![LayoutBox { rect: button, children: Vec::new() }],
+    }
+}
+
+#[test]
+fn events_reach_fragments_where_they_are_laid_out() {
+    let mut input = InputRouter::new();
+    let button = input.fragment(&[0]);
+    let presses = Rc::new(Cell::new(0));
+
+    input.on_event(button, EventPhase::Bubble, {
+        let presses = presses.clone();
+        move |_, cx| {
+            presses.set(presses.get() + 1);
+            cx.stop_propagation();
+        }
+    });
+
+    input.set_layout(&screen(Rect::new(100.0, 250.0, 100.0, 30.0)));
+    assert_eq!(input.hit_test((150.0, 260.0)), Some(button));
+    assert_eq!(input.hit_test((10.0, 10.0)), Some(input.fragment(&[])));
+
+    // Laying the tree out again moves the fragment, but keeps its id and handlers.
+    input.set_layout(&screen(Rect::new(0.0, 0.0, 100.0, 30.0)));
+    assert_eq!(input.fragment(&[0]), button);
+    assert!(input.dispatch(&UIEvent::PointerPressed { position: (10.0, 10.0), button: MouseButton::Left }));
+    assert!(!input.dispatch(&UIEvent::PointerPressed { position: (150.0, 260.0), button: MouseButton::Left }));
+    assert_eq!(presses.get(), 1);
+}
+
+#[test]
+fn fragments_that_are_not_laid_out_anymore_lose_their_handlers() {
+    let mut input = InputRouter::new();
+    let button = input.fragment(&[0]);
+    input.on_event(button, EventPhase::Bubble, |_, _| {});
+    input.set_layout(&screen(Rect::new(0.0, 0.0, 100.0, 30.0)));
+
+    input.set_layout(&LayoutBox { rect: Rect::new(0.0, 0.0, 300.0, 300.0), children: Vec::new() });
+    assert_ne!(input.fragment(&[0]), button);
+    assert!(!input.dispatch(&UIEvent::PointerPressed { position: (10.0, 10.0), button: MouseButton::Left }));
+}
@@ -0,0 +1,81 @@
+use std::path::PathBuf;
+use ui_composer::error::UIError;
+use ui_composer::ui::docks::layout_file::{FragmentNode, LayoutFile, DEFAULT_LAYOUT_NAME, LAYOUT_FILE_VERSION};
+use ui_composer::ui::docks::workspace::{DockNode, PanelId};
+use ui_composer::ui::docks::UIFragment;
+use ui_composer::ui::geometry::FlowDirection;
+
+/// Layout files written by earlier versions of the crate, in `assets/layouts`.
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("assets/layouts").join(name)
+}
+
+#[test]
+fn version_1_files_load_as_the_default_layout() -> Result<(), UIError> {
+    let file = LayoutFile::load(fixture("v1.ron"))?;
+
+    assert_eq!(file.version, LAYOUT_FILE_VERSION);
+    assert_eq!(file.names(), vec![DEFAULT_LAYOUT_NAME]);
+    let layout = file.layout(DEFAULT_LAYOUT_NAME).unwrap();
+    let DockNode::Split { direction, children } = &layout.arrangement.root else { panic!("The root is a split.") };
+    assert_eq!(*direction, FlowDirection::Forward);
+    assert_eq!(children[1].weight, 3.0);
+    assert_eq!(layout.arrangement.floating[0].tabs.panels, vec![PanelId::from("console")]);
+    assert!(layout.fragments.is_empty());
+    Ok(())
+}
+
+#[test]
+fn version_2_files_load_with_defaults_for_later_fields() -> Result<(), UIError> {
+    let file = LayoutFile::load(fixture("v2.ron"))?;
+
+    assert_eq!(file.version, LAYOUT_FILE_VERSION);
+    assert_eq!(file.names(), vec!["Editor", "Empty"]);
+    let layout = file.layout("Editor").unwrap();
+    let DockNode::Split { children, .. } = &layout.arrangement.root else { panic!("The root is a split.") };
+    let DockNode::Tabs(editor) = &children[0].node else { panic!("The first pane is a tab stack.") };
+    assert_eq!(editor.minimum_size, Some((200.0, 100.0)));
+
+    let Some(FragmentNode::List { gap, snap_to_pixels, children, .. }) = layout.fragments.get(&PanelId::from("editor")) else {
+        panic!("The editor has a fragment list.");
+    };
+    assert_eq!(*gap, 0.0);
+    assert!(!snap_to_pixels);
+    assert_eq!(children[1], FragmentNode::Leaf {
+        name: Some("text".to_owned()),
+        weight: 1.0,
+        minimum_size: (0.0, 0.0),
+        maximum_size: None,
+    });
+    let Some(UIFragment::Container(list)) = layout.fragment(&PanelId::from("editor")) else {
+        panic!("The editor's fragment is a container.");
+    };
+    assert_eq!(list.children().len(), 2);
+
+    // Saving it writes the current version, which loads the same.
+    assert_eq!(LayoutFile::from_ron(&file.to_ron()?)?, file);
+    Ok(())
+}
+
+#[test]
+fn versioned_files_without_layouts_are_rejected() {
+    for version in 1..=LAYOUT_FILE_VERSION {
+        match LayoutFile::from_ron(&format!("(version: {})", version)) {
+            Err(UIError::Arrangement(message)) => assert!(message.contains("layouts"), "{}", message),
+            other => panic!("Expected an arrangement error for version {}, got {:?}", version, other),
+        }
+    }
+
+    // An empty list is still a valid file.
+    let file = LayoutFile::from_ron(&format!("(version: {}, layouts: [])", LAYOUT_FILE_VERSION));
+    assert!(matches!(file, Ok(file) if file.layouts.is_empty()));
+}
+
+#[test]
+fn files_from_newer_versions_are_rejected() {
+    let source = format!("(version: {}, layouts: [])", LAYOUT_FILE_VERSION + 1);
+    match LayoutFile::from_ron(&source) {
+        Err(UIError::Arrangement(message)) => assert!(message.contains("version"), "{}", message),
+        other => panic!("Expected an arrangement error, got {:?}", other),
+    }
+}
@@ -0,0 +1,118 @@
+use std::ops::Range;
+
+use ui_composer::error::UIError;
+use ui_composer::renderer::engine::render_engine::{HeadlessRenderingEngineDescriptor, RenderingEngine};
+use ui_composer::renderer::formats::vertex::InstanceData;
+use ui_composer::renderer::modules::ui::{PrimitiveOpacity, PrimitiveRenderModule, PrimitiveSplice, INITIAL_INSTANCE_CAPACITY};
+
+fn opaque() -> InstanceData {
+    InstanceData { color: [1.0, 0.0, 0.0, 1.0], ..Default::default() }
+}
+
+fn shadowed() -> InstanceData {
+    InstanceData { shadow_color: [0.0, 0.0, 0.0, 0.35], ..opaque() }
+}
+
+#[test]
+fn primitives_are_classified_by_what_blends() {
+    assert_eq!(PrimitiveOpacity::of(&opaque()), PrimitiveOpacity::Opaque);
+    assert_eq!(PrimitiveOpacity::of(&shadowed()), PrimitiveOpacity::Translucent);
+    assert_eq!(PrimitiveOpacity::of(&InstanceData { color: [1.0, 0.0, 0.0, 0.5], ..opaque() }), PrimitiveOpacity::Translucent);
+    assert_eq!(
+        PrimitiveOpacity::of(&InstanceData { border_width: 2.0, border_color: [0.0, 0.0, 0.0, 0.5], ..opaque() }),
+        PrimitiveOpacity::Translucent
+    );
+    // A transparent border doesn't matter if there's no border.
+    assert_eq!(PrimitiveOpacity::of(&InstanceData { border_color: [0.0; 4], ..opaque() }), PrimitiveOpacity::Opaque);
+    assert_eq!(PrimitiveOpacity::of(&bytemuck::Zeroable::zeroed()), PrimitiveOpacity::Invisible);
+}
+
+#[tokio::test]
+async fn only_translucent_primitives_are_sorted() -> Result<(), UIError> {
+    let engine = RenderingEngine::new_headless(HeadlessRenderingEngineDescriptor {
+        size: (16, 16),
+        ..Default::default()
+    }).await?;
+
+    let mut module = PrimitiveRenderModule::from_gpu(&engine.gpu)?;
+    module.push_raw_primitives(&engine.gpu, &vec![opaque(), shadowed(), opaque()]);
+    assert_eq!(module.stats().translucent_count, 1);
+
+    // Filling the gap up to index 5 adds invisible primitives, which aren't drawn at all.
+    module.splice_primitives(&engine.gpu, PrimitiveSplice { range: 5..5, primitives: vec![shadowed()] })?;
+    assert_eq!(module.stats().instance_count, 6);
+    assert_eq!(module.stats().translucent_count, 2);
+
+    module.replace_primitives(&engine.gpu, 1, &[opaque()]);
+    assert_eq!(module.stats().translucent_count, 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn the_instance_buffer_grows_and_only_shrinks_when_enabled() -> Result<(), UIError> {
+    let engine = RenderingEngine::new_headless(HeadlessRenderingEngineDescriptor {
+        size: (16, 16),
+        ..Default::default()
+    }).await?;
+
+    let mut module = PrimitiveRenderModule::from_gpu(&engine.gpu)?;
+    assert_eq!(module.stats().instance_capacity, INITIAL_INSTANCE_CAPACITY);
+
+    module.push_raw_primitives(&engine.gpu, &vec![opaque(); INITIAL_INSTANCE_CAPACITY * 3]);
+    assert_eq!(module.stats().instance_capacity, INITIAL_INSTANCE_CAPACITY * 4);
+    assert_eq!(module.stats().instance_buffer_reallocations, 1);
+
+    module.push_raw_primitives(&engine.gpu, &vec![opaque(); 4]);
+    assert_eq!(module.stats().instance_capacity, INITIAL_INSTANCE_CAPACITY * 4);
+    assert_eq!(module.stats().instance_buffer_reallocations, 1);
+
+    module.set_shrink_instance_buffer(true);
+    module.push_raw_primitives(&engine.gpu, &vec![opaque(); 4]);
+    assert_eq!(module.stats().instance_capacity, INITIAL_INSTANCE_CAPACITY);
+    assert_eq!(module.stats().instance_buffer_reallocations, 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn inverted_splices_are_rejected() -> Result<(), UIError> {
+    let engine = RenderingEngine::new_headless(HeadlessRenderingEngineDescriptor {
+        size: (16, 16),
+        ..Default::default()
+    }).await?;
+
+    let mut module = PrimitiveRenderModule::from_gpu(&engine.gpu)?;
+    module.push_raw_primitives(&engine.gpu, &vec![opaque(), opaque(), opaque()]);
+
+    let inverted = Range { start: 3, end: 1 };
+    let result = module.splice_primitives(&engine.gpu, PrimitiveSplice { range: inverted.clone(), primitives: vec![opaque()] });
+    assert!(matches!(result, Err(UIError::InvalidSplice(range)) if range == inverted));
+    assert_eq!(module.stats().instance_count, 3);
+    Ok(())
+}
+
+#[tokio::test]
+async fn higher_layers_are_drawn_over_closer_primitives_of_lower_layers() -> Result<(), UIError> {
+    let mut engine = RenderingEngine::new_headless(HeadlessRenderingEngineDescriptor {
+        size: (4, 4),
+        ..Default::default()
+    }).await?;
+
+    let square = |depth: f32, color: [f32; 4]| InstanceData {
+        transform: InstanceData::rect_transform([0.0, 0.0, depth], [4.0, 4.0]),
+        color,
+        ..Default::default()
+    };
+
+    let mut below = Box::new(PrimitiveRenderModule::from_gpu(&engine.gpu)?);
+    below.push_raw_primitives(&engine.gpu, &vec![square(0.1, [1.0, 0.0, 0.0, 1.0])]);
+    engine.add_render_module_to_layer(below, 0);
+
+    let mut above = Box::new(PrimitiveRenderModule::from_gpu(&engine.gpu)?);
+    above.push_raw_primitives(&engine.gpu, &vec![square(0.9, [0.0, 0.0, 1.0, 1.0])]);
+    engine.add_render_module_to_layer(above, 1);
+
+    engine.render()?;
+    let pixels = engine.read_frame_rgba().await?;
+    assert_eq!(&pixels[..4], &[0x00, 0x00, 0xFF, 0xFF]);
+    Ok(())
+}
@@ -0,0 +1,151 @@
+#![allow(non_snake_case)]
+
+use std::{collections::VecDeque, future::Future, ops::Range, pin::Pin, task::{Context, Poll}};
+use futures::task::noop_waker_ref;
+use ui_composer::error::UIError;
+use ui_composer::prelude::signals::signal::{always, Mutable, Signal, SignalExt};
+use ui_composer::renderer::{formats::vertex::InstanceData, modules::ui::SpliceReceiver};
+use ui_composer::ui::reactive::{Rerender, RerenderDriver};
+
+/// A component that emits a fixed list of rerenders, one per poll.
+struct Script(VecDeque<Rerender>);
+
+impl Signal for Script {
+    type Item = Rerender;
+
+    fn poll_change(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Rerender>> {
+        match self.get_mut().0.pop_front() {
+            Some(rerender) => Poll::Ready(Some(rerender)),
+            None => Poll::Pending,
+        }
+    }
+}
+
+fn primitive(tag: usize) -> InstanceData {
+    InstanceData { color: [tag as f32, 0.0, 0.0, 1.0], ..Default::default() }
+}
+
+fn Tagged(tag: Mutable<usize>, owned: usize) -> impl Signal<Item = Rerender> {
+    tag.signal().map(move |tag| Rerender::primitives(0..owned, vec![primitive(tag)]))
+}
+
+fn poll(driver: &mut RerenderDriver) {
+    let mut cx = Context::from_waker(noop_waker_ref());
+    assert!(Pin::new(driver).poll(&mut cx).is_pending());
+}
+
+fn received(splices: &SpliceReceiver) -> Vec<(Range<usize>, Vec<f32>)> {
+    splices
+        .try_iter()
+        .map(|splice| splice.expect("Every rerender is valid."))
+        .map(|splice| (splice.range, splice.primitives.iter().map(|p| p.color[0]).collect()))
+        .collect()
+}
+
+#[test]
+fn later_components_move_when_a_splice_changes_length() {
+    let grow = Mutable::new(1);
+    let first = Mutable::new(1);
+    let second = Mutable::new(2);
+
+    let first_signal = first.signal().map({
+        let grow = grow.clone();
+        move |tag| {
+            let length = grow.get();
+            Rerender::primitives(0..1, vec![primitive(tag); length])
+        }
+    });
+
+    let (mut driver, splices) = RerenderDriver::new(Script(VecDeque::from([
+        Rerender::nested(0..1, first_signal),
+        Rerender::nested(1..2, Tagged(second.clone(), 1)),
+    ])));
+
+    poll(&mut driver);
+    assert_eq!(received(&splices), vec![(0..1, vec![1.0]), (1..2, vec![2.0])]);
+
+    // The first component now renders three primitives instead of one...
+    grow.set(3);
+    first.set(3);
+    poll(&mut driver);
+    assert_eq!(received(&splices), vec![(0..1, vec![3.0, 3.0, 3.0])]);
+
+    // ...so the second one has moved after them.
+    second.set(4);
+    poll(&mut driver);
+    assert_eq!(received(&splices), vec![(3..4, vec![4.0])]);
+}
+
+#[test]
+fn rerenders_outside_of_the_owned_range_are_sent_as_errors() {
+    let tag = Mutable::new(1);
+
+    let (mut driver, splices) = RerenderDriver::new(Script(VecDeque::from([
+        Rerender::nested(2..3, Tagged(tag.clone(), 2)),
+    ])));
+
+    poll(&mut driver);
+    let sent: Vec<_> = splices.try_iter().collect();
+    assert!(
+        matches!(&sent[..], [Err(UIError::InvalidRerender { owned, range })] if *owned == (2..3) && *range == (0..2)),
+        "{:?}",
+        sent
+    );
+}
+
+#[test]
+fn children_of_finished_components_are_moved_and_removed_with_them() {
+    let version = Mutable::new(0);
+    let tag = Mutable::new(1);
+    let grow = Mutable::new(1);
+
+    // `always` emits once and finishes, like many containers do.
+    let child = tag.signal().map({
+        let grow = grow.clone();
+        move |tag| Rerender::primitives(0..1, vec![primitive(tag); grow.get()])
+    });
+    let mut child = Some(child);
+    let root = version.signal().map(move |version| match version {
+        0 => Rerender::nested(0..2, always(()).map({
+            let child = child.take().expect("The container is only created once.");
+            let mut child = Some(child);
+            move |_| Rerender::nested(0..1, child.take().expect("`always` only emits once."))
+        })),
+        _ => Rerender::primitives(0..4, vec![primitive(9)]),
+    });
+    let (mut driver, splices) = RerenderDriver::new(Script(VecDeque::from([
+        Rerender::nested(0..2, root),
+        Rerender::nested(2..3, Tagged(Mutable::new(2), 1)),
+    ])));
+
+    poll(&mut driver);
+    assert_eq!(received(&splices), vec![(0..1, vec![1.0]), (2..3, vec![2.0])]);
+
+    // The container has finished, but it still grows along with its child.
+    grow.set(3);
+    tag.set(3);
+    poll(&mut driver);
+    assert_eq!(received(&splices), vec![(0..1, vec![3.0, 3.0, 3.0])]);
+
+    // Rerendering over the container removes its child too, which doesn't splice anymore.
+    version.set(1);
+    poll(&mut driver);
+    assert_eq!(received(&splices), vec![(0..4, vec![9.0])]);
+    tag.set(4);
+    poll(&mut driver);
+    assert_eq!(received(&splices), vec![]);
+}
+
+#[test]
+fn nested_ranges_are_relative_to_their_parent() {
+    let tag = Mutable::new(5);
+
+    let (mut driver, splices) = RerenderDriver::new(Script(VecDeque::from([
+        Rerender::nested(4..8, Script(VecDeque::from([
+            Rerender::nested(2..3, Tagged(tag.clone(), 1)),
+        ]))),
+    ])));
+
+    poll(&mut driver);
+    assert_eq!(received(&splices), vec![(6..7, vec![5.0])]);
+}
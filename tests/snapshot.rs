@@ -0,0 +1,107 @@
+use std::path::PathBuf;
+use ui_composer::error::UIError;
+use ui_composer::renderer::engine::render_engine::{HeadlessRenderingEngineDescriptor, RenderingEngine};
+use ui_composer::renderer::fonts::FontRegistry;
+use ui_composer::renderer::modules::text::{FontFamily, TextId, TextItem, TextRenderModule};
+use ui_composer::renderer::modules::ui::to_linear_rgb;
+use ui_composer::renderer::snapshot::{assert_snapshot, compare_snapshot, Snapshot, SnapshotOptions, SnapshotOutcome};
+use ui_composer::renderer::{
+    formats::vertex::InstanceData,
+    modules::ui::PrimitiveRenderModule,
+};
+
+/// Renders the counter scene offscreen and compares it against `assets/snapshots/counter.png`.
+/// Run with `UI_COMPOSER_UPDATE_SNAPSHOTS=1` to accept a new golden image.
+#[tokio::test]
+async fn counter_matches_golden() -> Result<(), UIError> {
+    let mut engine = RenderingEngine::new_headless(HeadlessRenderingEngineDescriptor {
+        size: (300, 300),
+        ..Default::default()
+    }).await?;
+
+    let mut primitive_module = Box::new(PrimitiveRenderModule::from_gpu(&engine.gpu)?);
+    primitive_module.push_raw_primitives(&engine.gpu, &counter_instance_data());
+    engine.add_render_module(primitive_module);
+
+    let fonts = FontRegistry::without_system_fonts();
+    let mut text_module = Box::new(TextRenderModule::from_gpu(&engine.gpu, &fonts)?);
+    text_module.set_text(TextId(0), TextItem {
+        content: "Counter: 0".to_owned(),
+        position: (16.0, 16.0),
+        bounds: (268.0, 32.0),
+        family: FontFamily::Monospace,
+        size: 16.0,
+        line_height: 20.0,
+        color: [0x20, 0x20, 0x20, 0xFF],
+        ..Default::default()
+    });
+    engine.add_render_module_to_layer(text_module, 1);
+
+    let snapshot = Snapshot::capture(&mut engine).await?;
+    let outcome = assert_snapshot("counter", &snapshot, &SnapshotOptions {
+        golden_dir: concat!(env!("CARGO_MANIFEST_DIR"), "/assets/snapshots").into(),
+        // Text antialiasing varies a bit between GPUs.
+        channel_tolerance: 8,
+        ..Default::default()
+    })?;
+
+    assert!(matches!(outcome, SnapshotOutcome::Matched | SnapshotOutcome::Written(_)));
+    Ok(())
+}
+
+#[test]
+fn missing_golden_is_an_error_unless_updating() -> Result<(), UIError> {
+    let golden_dir = scratch_dir("missing_golden");
+    let snapshot = Snapshot { width: 2, height: 1, pixels: vec![0xFF, 0x00, 0x00, 0xFF, 0x00, 0x00, 0xFF, 0xFF] };
+    let mut options = SnapshotOptions {
+        golden_dir: golden_dir.clone(),
+        update: false,
+        ..Default::default()
+    };
+
+    let missing = compare_snapshot("square", &snapshot, &options);
+    assert!(matches!(missing, Err(UIError::SnapshotMissing { .. })));
+    assert!(!golden_dir.join("square.png").exists());
+
+    options.update = true;
+    assert_eq!(
+        compare_snapshot("square", &snapshot, &options)?,
+        SnapshotOutcome::Written(golden_dir.join("square.png"))
+    );
+
+    options.update = false;
+    assert_eq!(compare_snapshot("square", &snapshot, &options)?, SnapshotOutcome::Matched);
+
+    let other = Snapshot { pixels: vec![0x00; 8], ..snapshot };
+    let mismatch = assert_snapshot("square", &other, &options);
+    assert!(matches!(mismatch, Err(UIError::SnapshotMismatch { differing_pixels: 2, .. })));
+
+    std::fs::remove_dir_all(golden_dir)?;
+    Ok(())
+}
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ui-composer-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn counter_instance_data() -> Vec<InstanceData> {
+    vec![
+        InstanceData {
+            transform: InstanceData::rect_transform([0.0, 0.0, 0.999], [300.0, 300.0]),
+            color: to_linear_rgb(0xdedede),
+            ..Default::default()
+        },
+        InstanceData {
+            transform: InstanceData::rect_transform([(300.0-96.0)/2.0, 300.0-32.0-16.0, 0.9], [96.0, 32.0]),
+            color: to_linear_rgb(0xee2244),
+            corner_radii: [8.0; 4],
+            border_color: to_linear_rgb(0xa01030),
+            border_width: 2.0,
+            shadow_offset: [0.0, 4.0],
+            shadow_blur: 6.0,
+            shadow_color: [0.0, 0.0, 0.0, 0.35],
+        },
+    ]
+}